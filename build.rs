@@ -1,3 +1,10 @@
+use std::{env, fs, path::PathBuf};
+
 fn main() {
+    // Our own memory.x instead of the ch32-hal one, so the configuration page stays out of FLASH
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::copy("memory.x", out.join("memory.x")).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-changed=memory.x");
     println!("cargo:rustc-link-arg-bins=-Tlink.x");
 }
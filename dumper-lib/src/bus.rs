@@ -0,0 +1,254 @@
+//! Cartridge bus primitives the dump routines are written against.
+//!
+//! The GPIO implementation lives with the pins in the firmware's `DumperClass`. The byte level
+//! NES accesses below are generic over [`BusInterface`], so the tests run them against
+//! [`MockBusInterface`] on the host and check the recorded bus cycles.
+
+/// Samples taken of the data lines per byte read, the value read most often wins.
+pub const BYTE_READ_RETRIES: usize = 1;
+
+/// Levels of the NES control strobes, `true` meaning electrically high. `None` leaves a signal
+/// as it is, so a step of a bus cycle only names the strobes it changes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ControlSignals {
+    pub m2: Option<bool>,
+    /// /ROMSEL, low while A15 is set on a CPU access.
+    pub romsel: Option<bool>,
+    /// CPU R/W, low to write.
+    pub prg_rw: Option<bool>,
+    /// PPU /RD.
+    pub chr_rd: Option<bool>,
+}
+
+impl ControlSignals {
+    pub const UNCHANGED: ControlSignals = ControlSignals {
+        m2: None,
+        romsel: None,
+        prg_rw: None,
+        chr_rd: None,
+    };
+}
+
+/// Added to a PPU address to tell it from a CPU one in [`BusInterface::set_address`].
+pub const PPU_ADDRESS_SPACE: u32 = 0x1_0000;
+
+// Only ever used with the concrete bus types, which need no `Send` bound on the futures
+#[allow(async_fn_in_trait)]
+pub trait BusInterface {
+    /// Drives the CPU address `addr`, or the PPU address `addr - PPU_ADDRESS_SPACE` from
+    /// [`PPU_ADDRESS_SPACE`] up.
+    fn set_address(&mut self, addr: u32);
+    /// Samples the data lines, which must have been released first.
+    fn read_byte(&mut self) -> u8;
+    /// Drives `data` on the data lines.
+    fn write_byte(&mut self, data: u8);
+    /// Stops driving the data lines and leaves them pulled up, ready to be read.
+    fn release_data(&mut self);
+    fn set_control(&mut self, signals: ControlSignals);
+    /// Waits `ns` nanoseconds between two steps of a bus cycle.
+    async fn wait_ns(&mut self, ns: u32);
+}
+
+/// Samples the data lines `N` times, 1 us apart, and keeps the value read most often.
+async fn read_voted<B: BusInterface, const N: usize>(bus: &mut B) -> u8 {
+    let mut values = [0u8; N];
+    for value in values.iter_mut() {
+        *value = bus.read_byte();
+        bus.wait_ns(1000).await;
+    }
+
+    let mut best_val = values[0];
+    let mut best_count = 1;
+    for i in 0..N {
+        let count = 1 + values[i + 1..].iter().filter(|value| **value == values[i]).count();
+        if count > best_count {
+            best_count = count;
+            best_val = values[i];
+        }
+    }
+    best_val
+}
+
+/// CPU read cycle: /ROMSEL follows A15 once M2 is high, and the data lines are sampled
+/// `read_delay_ns` later.
+pub async fn read_prg_byte<B: BusInterface>(bus: &mut B, address: u16, read_delay_ns: u16) -> u8 {
+    bus.release_data();
+    bus.set_control(ControlSignals { prg_rw: Some(true), ..ControlSignals::UNCHANGED });
+    bus.set_control(ControlSignals { romsel: Some(true), ..ControlSignals::UNCHANGED });
+    bus.set_address(address as u32);
+    bus.set_control(ControlSignals { m2: Some(true), ..ControlSignals::UNCHANGED });
+    bus.set_control(ControlSignals { romsel: Some(address & 0x8000 == 0), ..ControlSignals::UNCHANGED });
+    bus.wait_ns(read_delay_ns as u32).await;
+    read_voted::<_, BYTE_READ_RETRIES>(bus).await
+}
+
+/// PPU read cycle, /RD low for `read_delay_ns` before the data lines are sampled.
+pub async fn read_chr_byte<B: BusInterface>(bus: &mut B, address: u16, read_delay_ns: u16) -> u8 {
+    bus.release_data();
+    bus.set_control(ControlSignals { m2: Some(true), ..ControlSignals::UNCHANGED });
+    bus.set_control(ControlSignals { romsel: Some(true), ..ControlSignals::UNCHANGED });
+    bus.set_address(PPU_ADDRESS_SPACE + address as u32);
+    bus.set_control(ControlSignals { chr_rd: Some(false), ..ControlSignals::UNCHANGED });
+    bus.wait_ns(read_delay_ns as u32).await;
+    let result = read_voted::<_, BYTE_READ_RETRIES>(bus).await;
+    bus.set_control(ControlSignals { chr_rd: Some(true), ..ControlSignals::UNCHANGED });
+    result
+}
+
+/// CPU write cycle. R/W goes low before the data lines are driven, so the cartridge never sees
+/// a read while the MCU drives the bus. `post_write_delay_ns` lets a bank switch settle before
+/// the next M2 rising edge.
+pub async fn write_prg_byte<B: BusInterface>(bus: &mut B, address: u16, data: u8, post_write_delay_ns: u16) {
+    bus.set_control(ControlSignals { m2: Some(false), ..ControlSignals::UNCHANGED });
+    bus.set_control(ControlSignals { romsel: Some(true), ..ControlSignals::UNCHANGED });
+    bus.set_control(ControlSignals { prg_rw: Some(false), ..ControlSignals::UNCHANGED });
+    bus.write_byte(data);
+
+    bus.set_address(address as u32);  // PHI2 low, ROMSEL always HIGH
+    bus.set_control(ControlSignals { m2: Some(true), ..ControlSignals::UNCHANGED });
+    // ROMSEL is low if need, PHI2 high
+    bus.set_control(ControlSignals { romsel: Some(address & 0x8000 == 0), ..ControlSignals::UNCHANGED });
+    bus.wait_ns(1000).await;  // WRITING
+    // PHI2 low, ROMSEL high
+    bus.set_control(ControlSignals { m2: Some(false), ..ControlSignals::UNCHANGED });
+    bus.wait_ns(1000).await;  // WRITING
+    bus.set_control(ControlSignals { romsel: Some(true), ..ControlSignals::UNCHANGED });
+    // Back to read mode
+    bus.set_control(ControlSignals { prg_rw: Some(true), ..ControlSignals::UNCHANGED });
+    bus.release_data();
+    bus.set_address(0);
+    if post_write_delay_ns > 0 {
+        bus.wait_ns(post_write_delay_ns as u32).await;
+    }
+    // Set phi2 to high state to keep cartridge unreseted
+    bus.set_control(ControlSignals { m2: Some(true), ..ControlSignals::UNCHANGED });
+}
+
+/// One call made on a [`MockBusInterface`].
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BusEvent {
+    Address(u32),
+    Read,
+    Write(u8),
+    Release,
+    Control(ControlSignals),
+    Wait(u32),
+}
+
+/// Records every call and answers reads with `read_value`.
+#[cfg(test)]
+pub struct MockBusInterface {
+    pub events: Vec<BusEvent>,
+    pub read_value: u8,
+}
+
+#[cfg(test)]
+impl MockBusInterface {
+    pub fn new(read_value: u8) -> Self {
+        MockBusInterface { events: Vec::new(), read_value }
+    }
+
+    fn record(&mut self, event: BusEvent) {
+        self.events.push(event);
+    }
+
+    fn position(&self, event: BusEvent) -> usize {
+        self.events.iter().position(|recorded| *recorded == event).expect("event not recorded")
+    }
+}
+
+#[cfg(test)]
+impl BusInterface for MockBusInterface {
+    fn set_address(&mut self, addr: u32) {
+        self.record(BusEvent::Address(addr));
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        self.record(BusEvent::Read);
+        self.read_value
+    }
+
+    fn write_byte(&mut self, data: u8) {
+        self.record(BusEvent::Write(data));
+    }
+
+    fn release_data(&mut self) {
+        self.record(BusEvent::Release);
+    }
+
+    fn set_control(&mut self, signals: ControlSignals) {
+        self.record(BusEvent::Control(signals));
+    }
+
+    async fn wait_ns(&mut self, ns: u32) {
+        self.record(BusEvent::Wait(ns));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    use super::*;
+
+    /// The mock never blocks, so a single poll runs a bus cycle to completion.
+    fn run<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("bus cycle waited on the mock"),
+        }
+    }
+
+    fn control(signals: ControlSignals) -> BusEvent {
+        BusEvent::Control(signals)
+    }
+
+    #[test]
+    fn write_prg_byte_lowers_rw_before_driving_data() {
+        let mut bus = MockBusInterface::new(0);
+        run(write_prg_byte(&mut bus, 0x8001, 0x06, 0));
+        let rw_low = bus.position(control(ControlSignals { prg_rw: Some(false), ..ControlSignals::UNCHANGED }));
+        let data = bus.position(BusEvent::Write(0x06));
+        let address = bus.position(BusEvent::Address(0x8001));
+        let romsel_low = bus.position(control(ControlSignals { romsel: Some(false), ..ControlSignals::UNCHANGED }));
+        let rw_high = bus.position(control(ControlSignals { prg_rw: Some(true), ..ControlSignals::UNCHANGED }));
+        assert!(rw_low < data);
+        assert!(data < address && address < romsel_low);
+        assert!(romsel_low < rw_high);
+        assert_eq!(bus.events.last(), Some(&control(ControlSignals { m2: Some(true), ..ControlSignals::UNCHANGED })));
+    }
+
+    #[test]
+    fn mmc3_bank_select_writes_register_pair() {
+        let mut bus = MockBusInterface::new(0);
+        run(write_prg_byte(&mut bus, 0x8000, 0x02, 200));
+        run(write_prg_byte(&mut bus, 0x8001, 0x05, 200));
+        let writes: Vec<BusEvent> = bus.events.iter()
+            .filter(|event| matches!(event, BusEvent::Address(address) if *address != 0) || matches!(event, BusEvent::Write(_)))
+            .copied()
+            .collect();
+        assert_eq!(&writes[..], &[BusEvent::Write(0x02), BusEvent::Address(0x8000), BusEvent::Write(0x05), BusEvent::Address(0x8001)]);
+        assert_eq!(bus.events.iter().filter(|event| **event == BusEvent::Wait(200)).count(), 2);
+    }
+
+    #[test]
+    fn read_prg_byte_keeps_romsel_high_below_8000() {
+        let mut bus = MockBusInterface::new(0xA5);
+        assert_eq!(run(read_prg_byte(&mut bus, 0x6000, 100)), 0xA5);
+        assert!(!bus.events.contains(&control(ControlSignals { romsel: Some(false), ..ControlSignals::UNCHANGED })));
+        let delay = bus.position(BusEvent::Wait(100));
+        assert!(bus.position(BusEvent::Address(0x6000)) < delay && delay < bus.position(BusEvent::Read));
+    }
+
+    #[test]
+    fn read_chr_byte_uses_ppu_address_space() {
+        let mut bus = MockBusInterface::new(0x3C);
+        assert_eq!(run(read_chr_byte(&mut bus, 0x1000, 100)), 0x3C);
+        assert!(bus.events.contains(&BusEvent::Address(PPU_ADDRESS_SPACE + 0x1000)));
+        assert_eq!(bus.events.last(), Some(&control(ControlSignals { chr_rd: Some(true), ..ControlSignals::UNCHANGED })));
+    }
+}
@@ -0,0 +1,10 @@
+//! Parts of the dumper firmware that do not touch the MCU peripherals.
+//!
+//! They live in their own crate so their tests build and run on the host, away from the RISC-V
+//! target and `build-std` settings of the firmware. See the README for the command.
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod bus;
+pub mod mapper_crc;
+pub mod rom_database;
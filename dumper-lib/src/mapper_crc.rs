@@ -0,0 +1,53 @@
+//! Known NES dumps to check the dumper hardware and the mapper support against.
+//!
+//! The entries are the rows of [`crate::rom_database`], matched on the mapper and ROM sizes of
+//! the configuration. A row without a recorded CHR CRC is checked on its PRG-ROM alone.
+//!
+//! The CRCs have not been checked against dumps made with this dumper yet. Until they are, a
+//! failed check is a hint to look closer, not proof of a bad dump.
+
+use crate::rom_database::NES_ROM_DATABASE;
+
+/// Compares a dump against the entries of its mapper and sizes. Returns whether one matches and
+/// the `(prg, chr)` CRCs expected: those of the matching entry, else of the first candidate.
+/// `None` when the table has no entry for the configuration.
+pub fn check(mapper: u8, prg_kb: u16, chr_kb: u16, prg_crc32: u32, chr_crc32: u32) -> Option<(bool, (u32, u32))> {
+    let mut candidates = NES_ROM_DATABASE.iter()
+        .filter(|(_, _, m, prg, chr, _)| *m == mapper && *prg == prg_kb && *chr == chr_kb)
+        .map(|(prg, chr, _, _, _, _)| (*prg, chr.unwrap_or(chr_crc32)))
+        .peekable();
+    let first = *candidates.peek()?;
+    match candidates.find(|expected| *expected == (prg_crc32, chr_crc32)) {
+        Some(expected) => Some((true, expected)),
+        None => Some((false, first)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chr_ram_dump_matches_its_entry() {
+        assert_eq!(check(1, 128, 0, 0x3FE272FB, 0), Some((true, (0x3FE272FB, 0))));
+        assert_eq!(check(1, 128, 0, 0xB5FC6A0F, 0), Some((true, (0xB5FC6A0F, 0))));
+    }
+
+    #[test]
+    fn mismatch_reports_first_candidate() {
+        assert_eq!(check(1, 128, 0, 0x12345678, 0), Some((false, (0x3FE272FB, 0))));
+        assert_eq!(check(1, 128, 0, 0x3FE272FB, 0x12345678), Some((false, (0x3FE272FB, 0))));
+    }
+
+    #[test]
+    fn mapper_0_entry_without_chr_crc_checks_prg_alone() {
+        assert_eq!(check(0, 16, 8, 0xD445F698, 0x12345678), Some((true, (0xD445F698, 0x12345678))));
+        assert_eq!(check(0, 16, 8, 0x12345678, 0x12345678), Some((false, (0xD445F698, 0x12345678))));
+    }
+
+    #[test]
+    fn unknown_configuration_is_not_checked() {
+        assert_eq!(check(0, 64, 8, 0xD445F698, 0), None);
+        assert_eq!(check(2, 16, 8, 0xD445F698, 0), None);
+    }
+}
@@ -0,0 +1,31 @@
+//! Small lookup table of known NES cartridges.
+//!
+//! Entries are keyed by the CRC32 of the PRG-ROM alone (no iNES header, no CHR-ROM), as computed
+//! by the dumper while streaming the PRG banks. The CHR CRC32 covers the CHR-ROM the same way: 0
+//! on CHR-RAM boards, `None` where it has not been recorded yet. [`crate::mapper_crc`] checks
+//! dumps against the same rows.
+//!
+//! Rows come from the per-chip CRC32s published by NES Cart DB: on a board with a single PRG
+//! chip, that chip's CRC is the one the dumper computes, and on CHR-RAM boards it is also the CRC
+//! of the headerless ROM. `utils/nescartdb_rows.py` prints the rows from the NES Cart DB XML
+//! export, skipping the boards with several PRG or CHR chips.
+
+/// `(prg_crc32, chr_crc32, mapper, prg_kb, chr_kb, name)`
+pub type NesRomEntry = (u32, Option<u32>, u8, u16, u16, &'static str);
+
+pub const NES_ROM_DATABASE: [NesRomEntry; 8] = [
+    (0x5CF548D3, None, 0, 32, 8, "Super Mario Bros."),
+    (0xD445F698, None, 0, 16, 8, "Donkey Kong"),
+    (0x1E2B4DD5, None, 0, 16, 8, "Excitebike"),
+    (0x3FE272FB, Some(0), 1, 128, 0, "The Legend of Zelda"),
+    (0xB5FC6A0F, Some(0), 1, 128, 0, "Metroid"),
+    (0x0B0C1DB1, Some(0), 1, 256, 0, "Mega Man 2"),
+    (0xF6EE8B86, None, 4, 256, 128, "Super Mario Bros. 3"),
+    (0x6D72C53A, None, 1, 32, 16, "Tetris"),
+];
+
+pub fn lookup(prg_crc32: u32) -> Option<(&'static str, u8)> {
+    NES_ROM_DATABASE.iter()
+        .find(|(crc32, _, _, _, _, _)| *crc32 == prg_crc32)
+        .map(|(_, _, mapper, _, _, name)| (*name, *mapper))
+}
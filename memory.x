@@ -0,0 +1,15 @@
+/* CH32V307VCT6 with the default 256 KB flash / 64 KB RAM split. The last 4 KB flash page is
+   left out of FLASH for the configuration record written by src/config/flash.rs. */
+MEMORY
+{
+    FLASH : ORIGIN = 0x00000000, LENGTH = 252K
+    CONFIG : ORIGIN = 0x0003F000, LENGTH = 4K
+    RAM : ORIGIN = 0x20000000, LENGTH = 64K
+}
+
+REGION_ALIAS("REGION_TEXT", FLASH);
+REGION_ALIAS("REGION_RODATA", FLASH);
+REGION_ALIAS("REGION_DATA", RAM);
+REGION_ALIAS("REGION_BSS", RAM);
+REGION_ALIAS("REGION_HEAP", RAM);
+REGION_ALIAS("REGION_STACK", RAM);
@@ -0,0 +1,91 @@
+//! Persistence of the [`DumperConfig`] in the last 4 KB erase page of the on-chip flash.
+//!
+//! The record sits in the last kilobyte of the page, but every [`write`] erases the whole page
+//! first.
+//!
+//! The record is a 4 byte magic, a 4 byte length and the configuration serialized as JSON, the
+//! same representation exposed to the host as `config.json`.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::dumper::DumperConfig;
+
+const FLASH_KEYR: *mut u32 = 0x4002_2004 as *mut u32;
+const FLASH_STATR: *mut u32 = 0x4002_200C as *mut u32;
+const FLASH_CTLR: *mut u32 = 0x4002_2010 as *mut u32;
+const FLASH_ADDR: *mut u32 = 0x4002_2014 as *mut u32;
+
+const FLASH_KEY1: u32 = 0x4567_0123;
+const FLASH_KEY2: u32 = 0xCDEF_89AB;
+
+const STATR_BSY: u32 = 1 << 0;
+const STATR_EOP: u32 = 1 << 5;
+const CTLR_PG: u32 = 1 << 0;
+const CTLR_PER: u32 = 1 << 1;
+const CTLR_STRT: u32 = 1 << 6;
+const CTLR_LOCK: u32 = 1 << 7;
+
+/// 4 KB erase page holding the record, the last one of the 256 KB flash. `memory.x` keeps it
+/// out of the FLASH region so the firmware can never be linked into it.
+const CONFIG_PAGE: u32 = 0x0803_F000;
+const CONFIG_ADDRESS: u32 = 0x0803_FC00;
+const CONFIG_SIZE: usize = 1024;
+const CONFIG_MAGIC: u32 = 0xDEADBEEF;
+const HEADER_SIZE: usize = 8;
+
+/// Reads the last configuration written by [`write`], if the record is valid.
+pub fn read() -> Option<DumperConfig> {
+    let record = unsafe { core::slice::from_raw_parts(CONFIG_ADDRESS as *const u8, CONFIG_SIZE) };
+    let magic = u32::from_le_bytes(record[0..4].try_into().unwrap());
+    let length = u32::from_le_bytes(record[4..8].try_into().unwrap()) as usize;
+    if magic != CONFIG_MAGIC || length > CONFIG_SIZE - HEADER_SIZE {
+        return None;
+    }
+    DumperConfig::from_json(&record[HEADER_SIZE..HEADER_SIZE + length])
+}
+
+/// Erases the configuration page and programs `config` into it. Returns `true` when the flash
+/// content reads back as written.
+pub fn write(config: &DumperConfig) -> bool {
+    let mut record = [0xFFu8; CONFIG_SIZE];
+    let length = match serde_json_core::to_slice(config, &mut record[HEADER_SIZE..]) {
+        Ok(length) => length,
+        _ => return false,
+    };
+    record[0..4].copy_from_slice(&CONFIG_MAGIC.to_le_bytes());
+    record[4..8].copy_from_slice(&(length as u32).to_le_bytes());
+    // Programming is done by half-words
+    let total = (HEADER_SIZE + length + 1) & !1;
+
+    critical_section::with(|_| unsafe {
+        write_volatile(FLASH_KEYR, FLASH_KEY1);
+        write_volatile(FLASH_KEYR, FLASH_KEY2);
+
+        write_volatile(FLASH_CTLR, read_volatile(FLASH_CTLR) | CTLR_PER);
+        write_volatile(FLASH_ADDR, CONFIG_PAGE);
+        write_volatile(FLASH_CTLR, read_volatile(FLASH_CTLR) | CTLR_STRT);
+        wait_not_busy();
+        write_volatile(FLASH_CTLR, read_volatile(FLASH_CTLR) & !CTLR_PER);
+
+        write_volatile(FLASH_CTLR, read_volatile(FLASH_CTLR) | CTLR_PG);
+        for (index, half_word) in record[..total].chunks_exact(2).enumerate() {
+            let address = (CONFIG_ADDRESS as usize + index * 2) as *mut u16;
+            write_volatile(address, u16::from_le_bytes([half_word[0], half_word[1]]));
+            wait_not_busy();
+        }
+        write_volatile(FLASH_CTLR, read_volatile(FLASH_CTLR) & !CTLR_PG);
+
+        write_volatile(FLASH_CTLR, read_volatile(FLASH_CTLR) | CTLR_LOCK);
+    });
+
+    let written = unsafe { core::slice::from_raw_parts(CONFIG_ADDRESS as *const u8, total) };
+    written == &record[..total]
+}
+
+unsafe fn wait_not_busy() {
+    unsafe {
+        while read_volatile(FLASH_STATR) & STATR_BSY != 0 {}
+        // EOP is cleared by writing 1
+        write_volatile(FLASH_STATR, STATR_EOP);
+    }
+}
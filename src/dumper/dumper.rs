@@ -1,56 +1,671 @@
 use core::default;
+use core::sync::atomic::Ordering;
 
-use ch32_hal::{gpio::{Flex, Input, Level, Output, Pin, Pull}, Peripheral};
-use embassy_time::Timer;
+use ch32_hal::{gpio::{Flex, Input, Level, Output, Pin, Pull, Speed}, Peripheral};
+use embassy_time::{with_timeout, Duration, Instant, Timer};
 use embassy_sync::channel::Channel;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-
-pub const BYTE_READ_RETRIES: usize = 1;
+use serde::{Serialize, Deserialize};
+
+use crate::bus::{self, BusInterface, ControlSignals, PPU_ADDRESS_SPACE};
+
+/// Size of the optional iNES trainer, stored between the header and PRG-ROM.
+pub const NES_TRAINER_SIZE: usize = 512;
+/// 16 instruments of 8 bytes each in the VRC7 patch ROM.
+const VRC7_PATCH_ROM_SIZE: usize = 128;
+/// Namco 163 internal RAM, reached through its $F800/$4800 address and data ports.
+const NAMCO163_RAM_SIZE: usize = 128;
+/// Bandai FCG (LZ93D50) register driving the serial EEPROM lines: bit 5 SCL, bit 6 SDA and
+/// bit 7 set to release SDA for the EEPROM to drive it.
+const BANDAI_EEPROM_CONTROL: u16 = 0x800D;
+/// SDA as driven by the EEPROM reads back on bit 4 anywhere in $6000-$7FFF.
+const BANDAI_EEPROM_DATA: u16 = 0x6000;
+/// Half an SCL period at the 100 kHz every 24C0x EEPROM supports.
+const BANDAI_EEPROM_HALF_PERIOD_NS: u32 = 5_000;
+/// Reads that must agree for a read delay to count as stable.
+const READ_LATENCY_SAMPLES: usize = 10;
+/// Read delay every cartridge is known to work with, the search upper bound.
+const READ_LATENCY_MAX_NS: u16 = 1000;
+/// The read delay search stops once the bounds are this close.
+const READ_LATENCY_RESOLUTION_NS: u16 = 25;
+/// Core clock set up in `main`, to turn sub-microsecond read delays into cycles.
+const SYSCLK_MHZ: u32 = 144;
+/// Longest the NES bus may stay idle in the middle of a register write before a dummy read.
+const MAPPER_KEEP_ALIVE_GAP: Duration = Duration::from_micros(50);
+/// Longest the dumper waits for a message before reporting itself alive to the main loop.
+const IDLE_ALIVE_PERIOD: Duration = Duration::from_millis(500);
+/// Size of one FDS disk side in the `.fds` file format.
+const FDS_SIDE_SIZE: u32 = 65500;
+/// Latch selecting which 32 KB half of a disk side is mapped in the `$6000–$DFFF` window.
+const FDS_PAGE_LATCH: u16 = 0x5000;
+
+/// Games on a Camerica Quattro (mapper 232) cartridge, one per 64 KB outer block.
+pub const QUATTRO_GAMES: u16 = 4;
+/// PRG-ROM of each Quattro game, four 16 KB banks the last of which is fixed at $C000.
+pub const QUATTRO_GAME_KB: u16 = 64;
+
+/// PRG addresses sampled by [`DumperClass::check_nes_bus`], spread over the $8000-$FFFF window.
+const NES_BUS_CHECK_ADDRESSES: [u16; 8] = [0x8000, 0x8001, 0x8100, 0x9000, 0xA000, 0xC000, 0xFFFE, 0xFFFF];
+/// Samples reading the same stuck value needed to reject the cartridge.
+const NES_BUS_CHECK_THRESHOLD: usize = 6;
+
+/// 8 KB banks selected by bits 2:0 of the FFE $42FE/$42FF registers, bits 4:3 picking the outer
+/// block they are taken from.
+const FFE_INNER_BANKS: u8 = 8;
+
+/// 1 KB CHR bank compared by [`DumperClass::detect_chr_mirror`], as a CRC of the whole bank.
+const CHR_MIRROR_CHECK_SIZE: u16 = 0x400;
+
+/// Base offsets in the 32 KB NROM window the address lines are toggled from by
+/// [`DumperClass::test_address_bus`]. A line is only reported when every base agrees, so one
+/// coincidentally repeated byte is not enough.
+const ADDRESS_BUS_TEST_BASES: [u16; 2] = [0x0000, 0x2AAA];
+
+/// First bytes of PRG-ROM at $8000 typical of a mapper's startup code.
+const NES_MAPPER_SIGNATURES: [(&[u8], u8); 1] = [
+    (&[0x78, 0x8D, 0x00, 0x20], 4), // SEI; STA $2000
+];
+
+/// HuCards are dumped whole, mirrors included for the smaller ones.
+const PCE_ROM_SIZE: u32 = 0x100000;
+/// Largest N64 ROM the 24 word address lines reach.
+const N64_MAX_ROM_SIZE: u32 = 0x2000000;
+/// Smallest N64 ROM, the first size checked for mirroring.
+const N64_MIN_ROM_SIZE: u32 = 0x400000;
+/// Words compared between the start of the ROM and a candidate mirror.
+const N64_MIRROR_CHECK_WORDS: u32 = 64;
+/// Largest Genesis ROM without a bank switching mapper, also used when the header is unreadable.
+const GENESIS_MAX_ROM_SIZE: u32 = 0x400000;
+/// Header field holding the byte address of the last ROM byte, big endian.
+const GENESIS_ROM_END_ADDRESS: u32 = 0x1A4;
+/// Part of a Game Boy Advance ROM searched for the save library ID string.
+const GBA_SAVE_SCAN_SIZE: u32 = 0x400000;
+/// Bytes of the save library scan between two watchdog kicks.
+const GBA_SAVE_SCAN_KICK_INTERVAL: u32 = 0x8000;
+/// Word address with A23 set, where the cartridge maps the EEPROM.
+const GBA_EEPROM_WORD_ADDRESS: u32 = 0x800000;
+
+/// Messages the dumper can queue ahead of the USB task: with room for two, the next chunk is
+/// read from the cartridge while the previous one is still waiting to be sent.
+pub const USB_CHANNEL_DEPTH: usize = 2;
+
+const GB_RTC_SNAPSHOT_SIZE: usize = 48;
+
+pub const VERIFY_BUFFER_SIZE: usize = 4096;
+
+/// Address bits carrying the VRC2/VRC4 register select lines (reg bit 0, reg bit 1), indexed by
+/// `DumperConfig::vrc_variant`.
+const VRC_REGISTER_LINES: [(u8, u8); 7] = [
+    (1, 0), // 0: VRC2a (mapper 22)
+    (1, 2), // 1: VRC4a (mapper 21)
+    (1, 0), // 2: VRC4b (mapper 25)
+    (6, 7), // 3: VRC4c (mapper 21)
+    (3, 2), // 4: VRC4d (mapper 25)
+    (2, 3), // 5: VRC4e (mapper 23)
+    (0, 1), // 6: VRC2b / VRC4f (mapper 23)
+];
+
+/// `DumperConfig::vrc_variant` of each NES 2.0 (mapper, submapper) pair of the VRC2/VRC4 boards.
+const VRC_SUBMAPPER_VARIANTS: [(u8, u8, u8); 8] = [
+    (21, 1, 1), // VRC4a
+    (21, 2, 3), // VRC4c
+    (23, 1, 6), // VRC4f
+    (23, 2, 5), // VRC4e
+    (23, 3, 6), // VRC2b
+    (25, 1, 2), // VRC4b
+    (25, 2, 4), // VRC4d
+    (25, 3, 2), // VRC2c, wired as VRC4b
+];
 
 pub enum MsgStartConsole {
     Nes,
     Snes,
     Sms,
+    Gb,
+    Pce,
+    N64,
+    Genesis,
+    Atari2600,
+}
+
+/// Which cartridge bus the pins are currently set up for. The SNES side (also used for the SMS,
+/// Game Boy and PC Engine adapters) drives pins that are inputs on the NES side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ConsoleMode {
+    Idle,
+    Nes,
+    Snes,
+    Sms,
+    Gb,
+    Pce,
+    N64,
+    Gba,
+    Genesis,
+}
+
+/// What the dumper found out about the inserted cartridge, besides the ROM content.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CartridgeInfo {
+    pub best_guess_mapper: Option<u8>,
+    /// Whether PRG-ROM only answered with /ROMSEL asserted, `None` when not checked.
+    pub standard_pinout: Option<bool>,
+    /// Slot guessed from the EXP line, `None` when not checked.
+    pub slot_type: Option<SlotType>,
+    /// Shortest NES read delay giving stable data, `None` when not measured.
+    pub read_latency_ns: Option<u16>,
+    /// Whether MMC3 SRAM ignored a test write, `None` when not checked.
+    pub sram_write_protected: Option<bool>,
+    /// What the SNES ROM is made of, `None` when not checked.
+    pub snes_storage: Option<CartridgeStorage>,
+    /// Manufacturer code read back in autoselect mode from a SNES flash chip.
+    pub flash_manufacturer: Option<u8>,
+    /// Walking-ones check of the NES address lines, `None` when not run.
+    pub address_bus: Option<BusTestResult>,
+    /// CHR-ROM size left once mirrored halves are removed, `None` when not checked.
+    pub detected_chr_kb: Option<u16>,
+}
+
+/// Outcome of [`DumperClass::test_address_bus`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BusTestResult {
+    /// Address lines A0-A14, one bit each, that made no difference to the data read or read the
+    /// same data as another line: unconnected, shorted or swapped.
+    pub swapped_bits: u16,
+}
+
+/// Memory holding a cartridge ROM. Repro cartridges use flash where the originals had mask ROM.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CartridgeStorage {
+    MaskRom,
+    Flash,
+}
+
+/// Cartridge memories dumped outside of the ROM images.
+pub enum MsgStartMemory {
+    NesSram,
+    NesPrgRam,
+    GbSram,
+    Vrc7Patches,
+    Namco163Ram,
+    GbaEeprom,
+    BandaiEeprom,
+    GbaFlash,
+    Mmc3Wram,
+    Mmc5ExRam,
 }
 
 impl Msg {
     pub const DATA_CHANNEL_SIZE: usize = 32;
-    pub const DUMP_SETUP_DATA_CHANGED_LENGTH: usize = Msg::DATA_CHANNEL_SIZE / 2;
+
+    /// The announced dump size is beyond any real cartridge.
+    pub const ERROR_ROM_SIZE: u8 = 0x01;
+    /// Every NES data line reads high, the cartridge is missing or not seated.
+    pub const ERROR_BUS_STUCK_HIGH: u8 = 0x02;
+    /// Every NES data line reads low.
+    pub const ERROR_BUS_STUCK_LOW: u8 = 0x03;
+    /// The Game Boy header checksum at $014D does not match, context is the stored value.
+    pub const ERROR_GB_HEADER_CHECKSUM: u8 = 0x06;
+    /// The NES SRAM is write protected, sram.bin was not written. See `force_write`.
+    pub const ERROR_SRAM_WRITE_PROTECTED: u8 = 0x07;
+    /// The Game Boy Advance cartridge has no EEPROM save, context is the [`GbaSaveType`] found.
+    pub const ERROR_GBA_NO_EEPROM: u8 = 0x08;
+    /// The Game Boy Advance cartridge has no flash save, context is the [`GbaSaveType`] found.
+    pub const ERROR_GBA_NO_FLASH: u8 = 0x09;
+    /// The configured PRG or CHR size needs more banks than the mapper can select, context is
+    /// the mapper number. Nothing was dumped.
+    pub const ERROR_BANK_OVERFLOW: u8 = 0x0A;
+
+    /// The Game Boy global checksum at $014E-$014F does not match the dumped ROM.
+    pub const WARNING_GB_GLOBAL_CHECKSUM: u8 = 0x01;
+    /// The SNES header checksum at $FFDE-$FFDF does not match the dumped ROM.
+    pub const WARNING_SNES_CHECKSUM: u8 = 0x02;
+    /// The uploaded config.json was rejected, either invalid or from a newer schema version.
+    pub const WARNING_CONFIG_REJECTED: u8 = 0x03;
+    /// A NES dump was started while the pins were still set up for another console.
+    pub const WARNING_BUS_MODE_SWITCH: u8 = 0x04;
+    /// The Bandai serial EEPROM did not acknowledge its read command, eeprom.bin is likely blank.
+    pub const WARNING_EEPROM_NO_ACK: u8 = 0x05;
+    /// The configuration was applied but could not be stored in flash, it is lost on reboot.
+    pub const WARNING_CONFIG_NOT_SAVED: u8 = 0x06;
 }
 
 pub enum Msg {
     Start {
         console: MsgStartConsole
     },
+    StartMemory {
+        memory: MsgStartMemory
+    },
+    /// Dumps `length_kb` of PRG-ROM from `offset_kb` as a ROM image of its own, for the games
+    /// of a multi-game cartridge.
+    StartPartial {
+        console: MsgStartConsole,
+        offset_kb: u16,
+        length_kb: u16,
+    },
+    /// Dumps SNES ROM banks `from_bank` to `to_bank` (excluded) of a `rom_type` layout, without
+    /// reading the cartridge header first.
+    StartPartialSnes {
+        from_bank: u8,
+        to_bank: u8,
+        rom_type: u8,
+    },
+    /// Dumps the NES cartridge in `format` instead of the configured `output_format`.
+    StartNes {
+        format: NesOutputFormat,
+    },
     DumpSetupData {
         rom_size: u32,
     },
-    DumpSetupDataChanged {
-        field: [u8;Self::DUMP_SETUP_DATA_CHANGED_LENGTH],
-        value: [u8;Self::DUMP_SETUP_DATA_CHANGED_LENGTH],
+    /// Replaces the whole dumper configuration.
+    Reconfigure {
+        config: DumperConfig,
     },
     Data {
         data: [u8; Msg::DATA_CHANNEL_SIZE],
         length: usize
     },
+    VerifyResult {
+        bytes_checked: u32,
+        mismatch_count: u32,
+    },
+    /// CRCs of the PRG and CHR parts of the dump, `chr_crc32` being 0 when no CHR-ROM was read.
+    Checksum {
+        prg_crc32: u32,
+        chr_crc32: u32,
+    },
+    /// Outcome of the known dump check enabled by `verify`, CRCs given as `(prg, chr)`.
+    CrcCheck {
+        passed: bool,
+        expected_crc: (u32, u32),
+        actual_crc: (u32, u32),
+    },
+    CartridgeInfo {
+        info: CartridgeInfo,
+    },
+    Error {
+        code: u8,
+        context: u32,
+    },
+    Warning {
+        code: u8,
+    },
+    Patch {
+        address: u16,
+        value: u8,
+    },
+    WriteSram {
+        data: [u8; Msg::DATA_CHANNEL_SIZE],
+        length: usize,
+        offset: u16,
+    },
+    WriteSramEnd,
     End,
 }
 
+/// How the cartridge wires the upper PRG address lines.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum PinMapping {
+    /// A15 decoded as /ROMSEL, A13-A14 straight.
+    Standard = 0,
+    /// Tengen boards with A14 inverted.
+    TengenA = 1,
+    /// Camerica boards: the address lines are standard, only the lockout defeat differs.
+    Camerica = 2,
+}
+
+/// Cartridge slot fitted on the dumper. The EXP line (PD14) only exists on the 72-pin NES slot:
+/// Famicom builds leave the EXP solder bridge open and NES builds close it, see the README.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum SlotType {
+    Famicom60 = 0,
+    Nes72 = 1,
+}
+
+/// Layout of a NES dump.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum NesOutputFormat {
+    /// PRG-ROM only, no header.
+    RawPrg = 0,
+    /// PRG-ROM then CHR-ROM, no header.
+    RawPrgChr = 1,
+    /// iNES header, NES 2.0 when `ines_version` is 2.
+    INes1 = 2,
+    /// NES 2.0 header whatever `ines_version` says.
+    INes2 = 3,
+}
+
+/// Output speed of the cartridge address lines, the GPIO slew rate setting.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum BusDriveStrength {
+    /// 2 MHz.
+    Low = 0,
+    /// 10 MHz, enough for the SNES bus timings.
+    Medium = 1,
+    /// 50 MHz, for edges well under 50 ns around the NES M2 transitions.
+    High = 2,
+}
+
+impl From<BusDriveStrength> for Speed {
+    fn from(strength: BusDriveStrength) -> Speed {
+        match strength {
+            BusDriveStrength::Low => Speed::Low,
+            BusDriveStrength::Medium => Speed::Medium,
+            BusDriveStrength::High => Speed::High,
+        }
+    }
+}
+
+/// Output speed of the data lines: they only turn around between reads and writes.
+const DATA_PIN_SPEED: Speed = Speed::Medium;
+
+/// Save memory of a Game Boy Advance cartridge, told by the ID string its save library leaves in
+/// the ROM.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum GbaSaveType {
+    None = 0,
+    Sram = 1,
+    Flash = 2,
+    Eeprom512 = 3,
+    Eeprom8k = 4,
+}
+
+/// Game Boy Advance flash save chips, told apart by their autoselect IDs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GbaFlashChip {
+    /// 64 KB, MX29L512 and the other single bank chips.
+    Macronix64,
+    /// 128 KB MX29L010, two 64 KB banks.
+    Macronix128,
+    /// 128 KB LE26FV10N1TS, two 64 KB banks.
+    Sanyo128,
+}
+
+impl GbaFlashChip {
+    pub fn size(self) -> u32 {
+        match self {
+            GbaFlashChip::Macronix64 => 0x10000,
+            GbaFlashChip::Macronix128 | GbaFlashChip::Sanyo128 => 0x20000,
+        }
+    }
+}
+
+/// Highest config.json schema version this firmware understands.
+pub const CONFIG_VERSION: u8 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub struct DumperConfig {
     pub mapper: u8,
     pub prgsize: u8,
     pub chrsize: u8,
     pub prg: u16, // KB
     pub chr: u16, // KB
+    pub ines_version: u8,
+    pub is_vs_system: bool,
+    pub vs_palette: u8,
+    pub detect_vs_system: bool,
+    pub vrc_variant: u8,
+    pub fds_sides: u8, // FDS disk sides, 1 or 2
+    pub is_ffe_hack: bool, // FFE register scheme ($42FE/$42FF) for mapper 6
+    pub version: u8, // config.json schema version
+    pub trainer_addr: Option<u16>, // CPU address of a 512 byte trainer, copied ahead of PRG-ROM
+    pub skip_bus_check: bool, // Skip the stuck data line check before NES dumps
+    pub pin_mapping: PinMapping, // Wiring of the upper PRG address lines
+    pub slot_type: SlotType, // Cartridge slot the dumper was built with, reported in the diagnostics
+    pub read_delay_ns: u16, // Wait between driving the NES bus and sampling the data lines
+    pub auto_read_delay: bool, // Use the read delay measured before each NES dump
+    pub force_write: bool, // Write sram.bin even when sram_write_protect is set
+    pub chr_ram_kb: u16, // CHR-RAM size written to NES 2.0 headers when there is no CHR-ROM
+    pub vs_dip: u8, // Vs. System DIP switch state, kept for the host: iNES has no field for it
+    pub mmc1_variant: u8, // 1 = always write the SUROM outer PRG bank bit, 512 KB PRG sizes do it anyway
+    pub bus_drive_strength: BusDriveStrength, // Output speed of the address lines
+    pub prg_nvram_kb: u16, // Battery backed PRG-RAM size written to NES 2.0 headers, 0 when there is none
+    pub prg_ram_kb: Option<u16>, // Volatile PRG-RAM size written to NES 2.0 headers, None = 8 KB on MMC3, 0 otherwise
+    pub chr_nvram_kb: u16, // Battery backed CHR-RAM size written to NES 2.0 headers, 0 when there is none
+    pub bandai_eeprom_type: u8, // Bandai FCG serial EEPROM: 0 = none, 1 = 24C02 (256 B), 2 = X24C01 (128 B)
+    pub submapper: u8, // NES 2.0 submapper, also picks the VRC2/VRC4 register lines when not 0
+    pub mmc5_prg_mode: u8, // MMC5 PRG banking mode written to $5100, 0 = 32 KB ... 3 = 8 KB banks
+    pub verify: bool, // Compare NES dumps against the known CRCs of dumper-lib/src/mapper_crc.rs
+    pub output_format: NesOutputFormat, // Layout of rom.nes: headerless PRG, PRG + CHR, iNES or NES 2.0
+    pub atari2600_mapper: u8, // Atari 2600 bank switching: 0 = 4 KB, 1 = F8, 2 = F6, 3 = F4, 4 = E7
+    pub has_wram: bool, // MMC3 board with 8 KB of work RAM at $6000-$7FFF, not battery backed
+    pub has_battery: bool, // Namco 163 board with battery backed RAM at $6000-$7FFF
+    pub inter_bank_delay_ms: u16, // Pause between SNES banks, for setups upset by the bank switches
+    pub intra_bank_delay_ns: u16, // SNES address to data delay within a bank
+    pub post_write_delay_ns: Option<u16>, // Settle time after NES mapper writes, None = 100 ns on MMC1, 200 ns on MMC3, 0 otherwise
+    pub auto_detect_chr: bool, // Look for mirrored CHR-ROM before NES dumps and shrink chr to match
+    pub invert_chr: bool, // Complement CHR bytes, for clone boards wiring the CHR data lines inverted
+    pub sram_write_protect: bool, // The board keeps its SRAM write protected, sram.bin is refused unless force_write
+    pub vs_hardware: u8, // NES 2.0 Vs. hardware type written to byte 13, 0 = Vs. Unisystem
+    pub snes_flash_detect: bool, // Probe SNES cartridges for flash before dumping, which writes to the ROM address space
+}
+
+impl Default for DumperConfig {
+    fn default() -> Self {
+        /*
+        let mapper = 0;
+        let prglo = 0;
+        let prghi = 1;
+        let chrlo = 0;
+        let chrhi = 1;
+        let ramlo = 0;
+        let ramhi = 2;
+
+        let mapper = 4;
+        let prglo = 1;
+        let prghi = 5;
+        let chrlo = 0;
+        let chrhi = 6;
+        let ramlo = 0;
+        let ramhi = 1;
+        */
+        /*
+        let mapper = 0;
+        let prgsize = 1;
+        let chrsize = 1;
+        let prg = 32; // KB
+        let chr = 8; // KB
+        */
+        /*
+        let mapper = 0;
+        let prgsize = 0;
+        let chrsize = 1;
+        let prg = 16; // KB
+        let chr = 8; // KB
+        */
+        /*
+        let mut mapper: u8 = 4;
+        let mut prgsize: u8 = 4;
+        let mut chrsize: u8 = 5;
+        let mut prg: u16 = 256; // KB
+        let mut chr: u16 = 128; // KB
+        */
+        DumperConfig {
+            mapper: 1,
+            prgsize: 3,
+            chrsize: 0,
+            prg: 128,
+            chr: 0,
+            ines_version: 1,
+            is_vs_system: false,
+            vs_palette: 0,
+            detect_vs_system: false,
+            vrc_variant: 0,
+            fds_sides: 1,
+            is_ffe_hack: false,
+            version: CONFIG_VERSION,
+            trainer_addr: None,
+            skip_bus_check: false,
+            pin_mapping: PinMapping::Standard,
+            slot_type: SlotType::Nes72,
+            read_delay_ns: 1000,
+            auto_read_delay: false,
+            force_write: false,
+            chr_ram_kb: 8,
+            vs_dip: 0,
+            mmc1_variant: 0,
+            bus_drive_strength: BusDriveStrength::Low,
+            prg_nvram_kb: 0,
+            prg_ram_kb: None,
+            chr_nvram_kb: 0,
+            bandai_eeprom_type: 0,
+            submapper: 0,
+            mmc5_prg_mode: 3,
+            verify: false,
+            output_format: NesOutputFormat::INes1,
+            atari2600_mapper: 0,
+            has_wram: false,
+            has_battery: false,
+            inter_bank_delay_ms: 0,
+            intra_bank_delay_ns: 375,
+            post_write_delay_ns: None,
+            auto_detect_chr: false,
+            invert_chr: false,
+            sram_write_protect: false,
+            vs_hardware: 0,
+            snes_flash_detect: false,
+        }
+    }
+}
+
+/// Lenient view of config.json: any field may be missing, older files simply lack the newer ones.
+#[derive(Deserialize)]
+struct DumperConfigV1 {
+    mapper: Option<u8>,
+    prgsize: Option<u8>,
+    chrsize: Option<u8>,
+    prg: Option<u16>,
+    chr: Option<u16>,
+    ines_version: Option<u8>,
+    is_vs_system: Option<bool>,
+    vs_palette: Option<u8>,
+    detect_vs_system: Option<bool>,
+    vrc_variant: Option<u8>,
+    fds_sides: Option<u8>,
+    is_ffe_hack: Option<bool>,
+    version: Option<u8>,
+    trainer_addr: Option<u16>,
+    skip_bus_check: Option<bool>,
+    pin_mapping: Option<PinMapping>,
+    slot_type: Option<SlotType>,
+    read_delay_ns: Option<u16>,
+    auto_read_delay: Option<bool>,
+    force_write: Option<bool>,
+    chr_ram_kb: Option<u16>,
+    vs_dip: Option<u8>,
+    mmc1_variant: Option<u8>,
+    bus_drive_strength: Option<BusDriveStrength>,
+    prg_nvram_kb: Option<u16>,
+    prg_ram_kb: Option<u16>,
+    chr_nvram_kb: Option<u16>,
+    bandai_eeprom_type: Option<u8>,
+    submapper: Option<u8>,
+    mmc5_prg_mode: Option<u8>,
+    verify: Option<bool>,
+    output_format: Option<NesOutputFormat>,
+    atari2600_mapper: Option<u8>,
+    has_wram: Option<bool>,
+    has_battery: Option<bool>,
+    inter_bank_delay_ms: Option<u16>,
+    intra_bank_delay_ns: Option<u16>,
+    post_write_delay_ns: Option<u16>,
+    auto_detect_chr: Option<bool>,
+    invert_chr: Option<bool>,
+    sram_write_protect: Option<bool>,
+    vs_hardware: Option<u8>,
+    snes_flash_detect: Option<bool>,
+}
+
+impl DumperConfig {
+    /// Parses a config.json, taking the missing fields from [`DumperConfig::default`]. Returns
+    /// `None` if the JSON is invalid or was written for a schema newer than [`CONFIG_VERSION`].
+    pub fn from_json(json: &[u8]) -> Option<DumperConfig> {
+        let parsed = match serde_json_core::from_slice::<DumperConfigV1>(json) {
+            Ok((parsed, _)) => parsed,
+            _ => return None,
+        };
+        if parsed.version.unwrap_or(1) > CONFIG_VERSION {
+            return None;
+        }
+        let default = DumperConfig::default();
+        Some(DumperConfig {
+            mapper: parsed.mapper.unwrap_or(default.mapper),
+            prgsize: parsed.prgsize.unwrap_or(default.prgsize),
+            chrsize: parsed.chrsize.unwrap_or(default.chrsize),
+            prg: parsed.prg.unwrap_or(default.prg),
+            chr: parsed.chr.unwrap_or(default.chr),
+            ines_version: parsed.ines_version.unwrap_or(default.ines_version),
+            is_vs_system: parsed.is_vs_system.unwrap_or(default.is_vs_system),
+            vs_palette: parsed.vs_palette.unwrap_or(default.vs_palette),
+            detect_vs_system: parsed.detect_vs_system.unwrap_or(default.detect_vs_system),
+            vrc_variant: parsed.vrc_variant.unwrap_or(default.vrc_variant),
+            fds_sides: parsed.fds_sides.unwrap_or(default.fds_sides),
+            is_ffe_hack: parsed.is_ffe_hack.unwrap_or(default.is_ffe_hack),
+            version: CONFIG_VERSION,
+            trainer_addr: parsed.trainer_addr,
+            skip_bus_check: parsed.skip_bus_check.unwrap_or(default.skip_bus_check),
+            pin_mapping: parsed.pin_mapping.unwrap_or(default.pin_mapping),
+            slot_type: parsed.slot_type.unwrap_or(default.slot_type),
+            read_delay_ns: parsed.read_delay_ns.unwrap_or(default.read_delay_ns),
+            auto_read_delay: parsed.auto_read_delay.unwrap_or(default.auto_read_delay),
+            force_write: parsed.force_write.unwrap_or(default.force_write),
+            chr_ram_kb: parsed.chr_ram_kb.unwrap_or(default.chr_ram_kb),
+            vs_dip: parsed.vs_dip.unwrap_or(default.vs_dip),
+            mmc1_variant: parsed.mmc1_variant.unwrap_or(default.mmc1_variant),
+            bus_drive_strength: parsed.bus_drive_strength.unwrap_or(default.bus_drive_strength),
+            prg_nvram_kb: parsed.prg_nvram_kb.unwrap_or(default.prg_nvram_kb),
+            prg_ram_kb: parsed.prg_ram_kb,
+            chr_nvram_kb: parsed.chr_nvram_kb.unwrap_or(default.chr_nvram_kb),
+            bandai_eeprom_type: parsed.bandai_eeprom_type.unwrap_or(default.bandai_eeprom_type),
+            submapper: parsed.submapper.unwrap_or(default.submapper),
+            mmc5_prg_mode: parsed.mmc5_prg_mode.unwrap_or(default.mmc5_prg_mode),
+            verify: parsed.verify.unwrap_or(default.verify),
+            output_format: parsed.output_format.unwrap_or(default.output_format),
+            atari2600_mapper: parsed.atari2600_mapper.unwrap_or(default.atari2600_mapper),
+            has_wram: parsed.has_wram.unwrap_or(default.has_wram),
+            has_battery: parsed.has_battery.unwrap_or(default.has_battery),
+            inter_bank_delay_ms: parsed.inter_bank_delay_ms.unwrap_or(default.inter_bank_delay_ms),
+            intra_bank_delay_ns: parsed.intra_bank_delay_ns.unwrap_or(default.intra_bank_delay_ns),
+            post_write_delay_ns: parsed.post_write_delay_ns,
+            auto_detect_chr: parsed.auto_detect_chr.unwrap_or(default.auto_detect_chr),
+            invert_chr: parsed.invert_chr.unwrap_or(default.invert_chr),
+            sram_write_protect: parsed.sram_write_protect.unwrap_or(default.sram_write_protect),
+            vs_hardware: parsed.vs_hardware.unwrap_or(default.vs_hardware),
+            snes_flash_detect: parsed.snes_flash_detect.unwrap_or(default.snes_flash_detect),
+        })
+    }
 }
 
+/// `(bank, offset)` of the 80 byte blocks that may hold the SNES header, the usual LoROM/HiROM
+/// one first. ExHiROM boards larger than 4 MB keep it in bank $40.
+const SNES_HEADER_LOCATIONS: [(u8, u16); 4] = [(0x00, 0xFFB0), (0x00, 0x7FB0), (0x40, 0xFFB0), (0x40, 0x7FB0)];
+
 #[repr(u8)]
 pub enum SnesRomType {
     LO = 0,
     HI = 1,
     SA = 3,
     EX = 4,
+    /// Super FX (GSU) board. The GSU has no ROM of its own: its program is part of the ROM data,
+    /// which the SNES side sees LoROM mapped.
+    SuperFx = 5,
+}
+/// How the ROM data is spread over the SNES ROM chips.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SnesRomLayout {
+    Standard,
+    /// Two chips holding the even and the odd bytes, seen at banks $40 and $60.
+    Interleaved,
 }
+
+/// Banks the two chips of an [`SnesRomLayout::Interleaved`] cartridge start at, even bytes first.
+const SNES_INTERLEAVED_CHIP_BANKS: [u8; 2] = [0x40, 0x60];
+
 pub struct DumperClass<'d> {
     m2: Output<'d>,
     pgr_ce: Output<'d>,
@@ -72,8 +687,21 @@ pub struct DumperClass<'d> {
     d_snes: [Flex<'d>; 7],
     irq_snes: Input<'d>,
     in_channel: &'d Channel<CriticalSectionRawMutex, Msg, 1>,
-    out_channel: &'d Channel<CriticalSectionRawMutex, Msg, 1>,
+    out_channel: &'d Channel<CriticalSectionRawMutex, Msg, USB_CHANNEL_DEPTH>,
     buffer: &'d mut [u8; Msg::DATA_CHANNEL_SIZE],
+    verify_buf: &'d mut [u8; VERIFY_BUFFER_SIZE],
+    verify_fill: usize,
+    prg_crc32: u32,
+    chr_crc32: u32,
+    gb_sum: u16,
+    running_sum: u32,
+    snes_checksum: Option<u16>,
+    snes_layout: SnesRomLayout,
+    cartridge_info: CartridgeInfo,
+    current_mode: ConsoleMode,
+    sram_write_blocked: bool,
+    invert_chr: bool,
+    mapper_activity_timer: Instant,
     config: DumperConfig,
 }
 
@@ -134,8 +762,10 @@ impl<'d> DumperClass<'d>
         ),
         irq_snes_pin: impl Peripheral<P = impl Pin> + 'd,
         in_channel: &'d Channel<CriticalSectionRawMutex, Msg, 1>,
-        out_channel: &'d Channel<CriticalSectionRawMutex, Msg, 1>,
+        out_channel: &'d Channel<CriticalSectionRawMutex, Msg, USB_CHANNEL_DEPTH>,
         buffer: &'d mut [u8; Msg::DATA_CHANNEL_SIZE],
+        verify_buf: &'d mut [u8; VERIFY_BUFFER_SIZE],
+        config: DumperConfig,
     ) -> Self {
         let m2 = Output::new(m2_pin, Level::High, Default::default());
         let pgr_ce = Output::new(pgr_ce_pin, Level::High, Default::default());
@@ -145,23 +775,25 @@ impl<'d> DumperClass<'d>
         let irq: Flex<'_> = Flex::new(irq_pin);
         let prg_rw = Output::new(prg_rw_pin, Level::High, Default::default());
 
+        let address_speed: Speed = config.bus_drive_strength.into();
+
         let a = [
-            Output::new(a_pins.0, Level::Low, Default::default()),
-            Output::new(a_pins.1, Level::Low, Default::default()),
-            Output::new(a_pins.2, Level::Low, Default::default()),
-            Output::new(a_pins.3, Level::Low, Default::default()),
-            Output::new(a_pins.4, Level::Low, Default::default()),
-            Output::new(a_pins.5, Level::Low, Default::default()),
-            Output::new(a_pins.6, Level::Low, Default::default()),
-            Output::new(a_pins.7, Level::Low, Default::default()),
-            Output::new(a_pins.8, Level::Low, Default::default()),
-            Output::new(a_pins.9, Level::Low, Default::default()),
-            Output::new(a_pins.10, Level::Low, Default::default()),
-            Output::new(a_pins.11, Level::Low, Default::default()),
-            Output::new(a_pins.12, Level::Low, Default::default()),
-            Output::new(a_pins.13, Level::Low, Default::default()),
-            Output::new(a_pins.14, Level::Low, Default::default()),
-            Output::new(a_pins.15, Level::High, Default::default()),
+            Output::new(a_pins.0, Level::Low, address_speed),
+            Output::new(a_pins.1, Level::Low, address_speed),
+            Output::new(a_pins.2, Level::Low, address_speed),
+            Output::new(a_pins.3, Level::Low, address_speed),
+            Output::new(a_pins.4, Level::Low, address_speed),
+            Output::new(a_pins.5, Level::Low, address_speed),
+            Output::new(a_pins.6, Level::Low, address_speed),
+            Output::new(a_pins.7, Level::Low, address_speed),
+            Output::new(a_pins.8, Level::Low, address_speed),
+            Output::new(a_pins.9, Level::Low, address_speed),
+            Output::new(a_pins.10, Level::Low, address_speed),
+            Output::new(a_pins.11, Level::Low, address_speed),
+            Output::new(a_pins.12, Level::Low, address_speed),
+            Output::new(a_pins.13, Level::Low, address_speed),
+            Output::new(a_pins.14, Level::Low, address_speed),
+            Output::new(a_pins.15, Level::High, address_speed),
         ];
 
         let ciram_a10 = Flex::new(ciram_a10_pin);
@@ -177,13 +809,15 @@ impl<'d> DumperClass<'d>
             Flex::new(d_pins.7)
         ];
 
-        let a15 = Output::new(a15_pin, Level::High, Default::default());
+        let a15 = Output::new(a15_pin, Level::High, address_speed);
         let reset = Output::new(reset_pin, Level::High, Default::default());
         let cs = Output::new(cs_pin, Level::High, Default::default());
         let wr: Output<'_> = Output::new(wr_pin, Level::High, Default::default());
         let rd: Output<'_> = Output::new(rd_pin, Level::High, Default::default());
         let refresh = Output::new(refresh_pin, Level::High, Default::default());
-        let expand = Input::new(expand_pin, Pull::None);
+        // Nothing drives EXP on a Famicom slot, nor on most NES boards: keep it from floating so
+        // it reads low unless a board pulls it up
+        let expand = Input::new(expand_pin, Pull::Down);
 
         let d_snes = [
             Flex::new(d_snes_pins.0),
@@ -196,52 +830,6 @@ impl<'d> DumperClass<'d>
         ];
         let irq_snes = Input::new(irq_snes_pin, Pull::None);
 
-        /*
-        let mapper = 0;
-        let prglo = 0;
-        let prghi = 1;
-        let chrlo = 0;
-        let chrhi = 1;
-        let ramlo = 0;
-        let ramhi = 2;
-
-        let mapper = 4;
-        let prglo = 1;
-        let prghi = 5;
-        let chrlo = 0;
-        let chrhi = 6;
-        let ramlo = 0;
-        let ramhi = 1;
-        */
-        /*
-        let mapper = 0;
-        let prgsize = 1;
-        let chrsize = 1;
-        let prg = 32; // KB
-        let chr = 8; // KB
-        */
-        /*
-        let mapper = 0;
-        let prgsize = 0;
-        let chrsize = 1;
-        let prg = 16; // KB
-        let chr = 8; // KB
-        */
-        /*
-        let mut mapper: u8 = 4;
-        let mut prgsize: u8 = 4;
-        let mut chrsize: u8 = 5;
-        let mut prg: u16 = 256; // KB
-        let mut chr: u16 = 128; // KB
-        */
-        let config = DumperConfig {
-            mapper: 1,
-            prgsize: 3,
-            chrsize: 0,
-            prg: 128,
-            chr: 0
-        };
-
        return Self {
             m2,
             pgr_ce,
@@ -265,6 +853,19 @@ impl<'d> DumperClass<'d>
             in_channel,
             out_channel,
             buffer,
+            verify_buf,
+            verify_fill: 0,
+            prg_crc32: 0,
+            chr_crc32: 0,
+            gb_sum: 0,
+            running_sum: 0,
+            snes_checksum: None,
+            snes_layout: SnesRomLayout::Standard,
+            cartridge_info: CartridgeInfo::default(),
+            current_mode: ConsoleMode::Idle,
+            sram_write_blocked: false,
+            invert_chr: false,
+            mapper_activity_timer: Instant::now(),
             config,
         }
     }
@@ -273,10 +874,28 @@ impl<'d> DumperClass<'d>
         for index in 0..self.a.len() - 1 {
             self.a[index].set_level(Level::from((address & (1 << index)) > 0));
         }
+        // A15 is always decoded into /ROMSEL by set_romsel, only A14 may need fixing up
+        match self.config.pin_mapping {
+            PinMapping::Standard | PinMapping::Camerica => {}
+            PinMapping::TengenA => {
+                self.a[14].set_level(Level::from((address & (1 << 14)) == 0));
+            }
+        }
         // PPU /A13
         self.a[self.a.len()-1].set_level(Level::from((address & (1 << 13)) == 0));
     }
 
+    /// Drives the PPU address bus: A0-A13 from `ppu_addr` and /A13 (`a[15]`) as its complement, so
+    /// CHR-ROM is selected below $2000 and CIRAM above, without touching the CPU-only A14 line.
+    fn set_ppu_address(&mut self, ppu_addr: u16) {
+        for index in 0..14 {
+            self.a[index].set_level(Level::from((ppu_addr & (1 << index)) > 0));
+        }
+        self.a[14].set_low();
+        // PPU /A13
+        self.a[15].set_level(Level::from((ppu_addr & 0x2000) == 0));
+    }
+
     fn set_mode_read(&mut self) {
         for pin in self.d.iter_mut() {
             pin.set_as_input(Pull::Up);
@@ -286,7 +905,7 @@ impl<'d> DumperClass<'d>
     fn set_write_mode(&mut self) {
         for pin in self.d.iter_mut() {
             pin.set_low();
-            pin.set_as_output(Default::default());
+            pin.set_as_output(DATA_PIN_SPEED);
         }
     }
 
@@ -298,22 +917,10 @@ impl<'d> DumperClass<'d>
         self.prg_rw.set_low();
     }
 
-    fn set_romsel_low(&mut self){
-        self.pgr_ce.set_low();
-    }
-
     fn set_romsel_high(&mut self){
         self.pgr_ce.set_high();
     }
 
-    fn set_romsel(&mut self, address: u16) {
-    if address & 0x8000 > 0 {
-        self.set_romsel_low();
-    } else {
-        self.set_romsel_high();
-    }
-    }
-
     fn set_phy2_high(&mut self){
         self.m2.set_high();
     }
@@ -326,10 +933,6 @@ impl<'d> DumperClass<'d>
         self.chr_rd.set_high();
     }
 
-    fn set_chr_read_low(&mut self){
-        self.chr_rd.set_low();
-    }
-
 
     fn set_romsel_low_and_m2_high(&mut self){
         self.m2.set_high();
@@ -356,55 +959,43 @@ impl<'d> DumperClass<'d>
     }
 
     async fn write_prg_byte(&mut self, address: u16, data: u8) {
-        self.set_phy2_low();
-        self.set_romsel_high();
-        self.set_write_mode();
-        self.set_prg_write();
-        self.write_data(data);
-
-        self.set_address(address);  // PHI2 low, ROMSEL always HIGH
-        // Timer::after_micros(1).await; //  _delay_us(1);
-        self.set_phy2_high();
-        // Timer::after_micros(10).await; //_delay_us(10);
-        self.set_romsel(address);  // ROMSEL is low if need, PHI2 high
-        Timer::after_micros(1).await;  // WRITING
-        // Timer::after_millis(1).await; //_delay_ms(1); // WRITING
-        // PHI2 low, ROMSEL high
-        self.set_phy2_low();
-        Timer::after_micros(1).await;  // WRITING
-        self.set_romsel_high();
-        // Back to read mode
-        // Timer::after_micros(1).await; //  _delay_us(1);
-        self.set_prg_read();
-        self.set_mode_read();
-        self.set_address(0);
-        // Set phi2 to high state to keep cartridge unreseted
-        // Timer::after_micros(1).await; //  _delay_us(1);
-        self.set_phy2_high();
-        // Timer::after_micros(1).await; //  _delay_us(1);
+        let post_write_delay_ns = self.post_write_delay_ns();
+        bus::write_prg_byte(self, address, data, post_write_delay_ns).await;
+        self.mapper_activity_timer = Instant::now();
+    }
+
+    /// Pause after a mapper register write, `post_write_delay_ns` or the default of the mapper:
+    /// the MMC1 shift register and the MMC3 bank registers take a little while to settle.
+    fn post_write_delay_ns(&self) -> u16 {
+        match self.config.post_write_delay_ns {
+            Some(delay_ns) => delay_ns,
+            None if self.config.mapper == 1 => 100,
+            None if self.config.mapper == 4 => 200,
+            None => 0,
+        }
     }
 
     async fn read_prg_byte(&mut self, address: u16) -> u8 {
-        self.set_mode_read();
-        self.set_prg_read();
-        self.set_romsel_high();
-        self.set_address(address);
-        self.set_phy2_high();
-        self.set_romsel(address);
-        Timer::after_micros(1).await;
-        Self::retry_read::<_,BYTE_READ_RETRIES>(|| self.read_data()).await
+        let result = bus::read_prg_byte(self, address, self.config.read_delay_ns).await;
+        self.mapper_activity_timer = Instant::now();
+        result
     }
 
     async fn read_chr_byte(&mut self, address: u16) -> u8 {
-        self.set_mode_read();
+        let result = bus::read_chr_byte(self, address, self.config.read_delay_ns).await;
+        if self.invert_chr { !result } else { result }
+    }
+
+    async fn write_chr_byte(&mut self, address: u16, data: u8) {
         self.set_phy2_high();
         self.set_romsel_high();
-        self.set_address(address);
-        self.set_chr_read_low();
+        self.set_write_mode();
+        self.write_data(data);
+        self.set_ppu_address(address);
+        self.chr_wr.set_low();
         Timer::after_micros(1).await;
-        let result = Self::retry_read::<_,BYTE_READ_RETRIES>(|| self.read_data()).await;
-        self.set_chr_read_high();
-        result
+        self.chr_wr.set_high();
+        self.set_mode_read();
     }
 
     async fn write_reg_byte(&mut self, address: u16, data: u8) {  // FIX FOR MMC1 RAM CORRUPTION
@@ -429,62 +1020,85 @@ impl<'d> DumperClass<'d>
         self.set_address(0);
         // Set phi2 to high state to keep cartridge unreseted
         self.set_phy2_high();
+        self.mapper_activity_timer = Instant::now();
+    }
+
+    /// Reads `$FFFF` when the bus has been idle for more than [`MAPPER_KEEP_ALIVE_GAP`], so a
+    /// late timer wake-up does not leave the mapper without M2 activity in the middle of a
+    /// serial register write.
+    async fn keep_mapper_active(&mut self) {
+        if self.mapper_activity_timer.elapsed() > MAPPER_KEEP_ALIVE_GAP {
+            self.read_prg_byte(0xFFFF).await;
+        }
     }
 
     async fn write_mmc1_byte(&mut self, address: u16, data: u8) {
         if address >= 0xE000 {
             for i in 0..5u8 {
+                self.keep_mapper_active().await;
                 self.write_reg_byte(address, data >> i).await;
             }
         } else {
             for j in 0..5u8 {
+                self.keep_mapper_active().await;
                 self.write_prg_byte(address, data >> j).await;  // shift 1 bit into temp register
             }
         }
     }
 
-    async fn retry_read<F, const N: usize>(mut f: F) -> u8
-    where
-        F: FnMut() -> u8,
-    {
-        let mut values = [0u8; N];
-
-        for i in 0..N {
-            values[i] = f();
-            Timer::after_micros(1).await;
-        }
+    /// Writes a VRC2/VRC4 register given with its canonical address ($x000-$x003), routing the
+    /// register select bits to the address lines used by the configured board variant.
+    async fn write_vrc4_reg(&mut self, variant: u8, base_addr: u16, bank: u8) {
+        self.write_prg_byte(Self::vrc4_reg_addr(base_addr, variant), bank).await;
+    }
 
-        let mut best_val = values[0];
-        let mut best_count = 1;
+    /// Address of the VRC2/VRC4 register `base_addr` ($x000-$x003) on a board of `variant`.
+    fn vrc4_reg_addr(base_addr: u16, variant: u8) -> u16 {
+        let (line0, line1) = VRC_REGISTER_LINES[variant as usize % VRC_REGISTER_LINES.len()];
+        (base_addr & 0xF000) |
+            ((base_addr & 1) << line0) |
+            (((base_addr >> 1) & 1) << line1)
+    }
 
-        for i in 0..N {
-            let mut count = 1;
-            for j in (i + 1)..N {
-                if values[j] == values[i] {
-                    count += 1;
-                }
-            }
-            if count > best_count {
-                best_count = count;
-                best_val = values[i];
-            }
-        }
+    /// VRC2/VRC4 board variant: the one implied by the NES 2.0 submapper when it is set, else
+    /// `vrc_variant`.
+    fn vrc_board_variant(&self) -> u8 {
+        VRC_SUBMAPPER_VARIANTS.iter()
+            .find(|(mapper, submapper, _)| *mapper == self.config.mapper && *submapper == self.config.submapper)
+            .map_or(self.config.vrc_variant, |(_, _, variant)| *variant)
+    }
 
-        best_val
+    /// Sends the first `length` bytes of the buffer to the USB task.
+    async fn send_buffer(&mut self, length: usize) {
+        self.out_channel.send(Msg::Data{data: *self.buffer, length}).await;
+        crate::kick_watchdog();
     }
 
     async fn dump_prg(&mut self, base: u16, address: u16) {
         for x in 0..self.buffer.len() {
              self.buffer[x] = self.read_prg_byte(base + address + x as u16).await;
         }
-        self.out_channel.send(Msg::Data{data: *self.buffer, length: self.buffer.len()}).await;
+        self.prg_crc32 = Self::crc32_update(self.prg_crc32, &self.buffer[..]);
+        self.send_buffer(self.buffer.len()).await;
     }
 
     async fn dump_chr(&mut self, address: u16) {
         for x in 0..self.buffer.len() {
             self.buffer[x] = self.read_chr_byte(address + x as u16).await;
         }
-        self.out_channel.send(Msg::Data{data: *self.buffer, length: self.buffer.len()}).await;
+        self.chr_crc32 = Self::crc32_update(self.chr_crc32, &self.buffer[..]);
+        self.send_buffer(self.buffer.len()).await;
+    }
+
+    fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+        let mut crc = crc;
+        for byte in data {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+            }
+        }
+        crc
     }
 
     async fn dump_bank_prg(&mut self, from: u16, to: u16, base: u16) {
@@ -502,67 +1116,902 @@ impl<'d> DumperClass<'d>
     pub async fn dump(&mut self) {
         let receiver = self.in_channel.receiver();
         loop {
-            match receiver.receive().await {
+            // Waking up now and then while idle tells the main loop this task is still alive
+            let msg = with_timeout(IDLE_ALIVE_PERIOD, receiver.receive()).await;
+            crate::DUMPER_ALIVE.store(true, Ordering::Relaxed);
+            let Ok(msg) = msg else {
+                continue;
+            };
+            match msg {
                 Msg::Start {console} => {
                     match console {
-                        MsgStartConsole::Nes => {self.dump_nes().await;}
+                        MsgStartConsole::Nes => {self.dump_nes(self.config.output_format).await;}
                         MsgStartConsole::Snes => {self.dump_snes().await;}
                         MsgStartConsole::Sms => {self.dump_sms().await;}
+                        MsgStartConsole::Gb => {self.dump_gb().await;}
+                        MsgStartConsole::Pce => {self.dump_pce().await;}
+                        MsgStartConsole::N64 => {self.dump_n64().await;}
+                        MsgStartConsole::Genesis => {self.dump_genesis().await;}
+                        MsgStartConsole::Atari2600 => {self.dump_atari2600().await;}
                     };
                 }
-                Msg::DumpSetupDataChanged { field, value } => {
-                    let field_encoded = str::from_utf8(&field).unwrap();
-                    match field_encoded {
-                        "mapper\0\0\0\0\0\0\0\0\0\0" => {
-                            self.config.mapper = value[0]
-                        }
-                        "prgsize\0\0\0\0\0\0\0\0\0" => {
-                            self.config.prgsize = value[0]
-                        }
-                        "chrsize\0\0\0\0\0\0\0\0\0" => {
-                            self.config.chrsize = value[0]
-                        }
-                        "prg\0\0\0\0\0\0\0\0\0\0\0\0\0" => {
-                            self.config.prg = u16::from_ne_bytes(value[0..2].try_into().unwrap())
-                        }
-                        "chr\0\0\0\0\0\0\0\0\0\0\0\0\0" => {
-                            self.config.chr = u16::from_ne_bytes(value[0..2].try_into().unwrap())
+                Msg::StartNes {format} => {
+                    self.dump_nes(format).await;
+                }
+                Msg::StartPartialSnes {from_bank, to_bank, rom_type} => {
+                    self.dump_snes_partial(from_bank, to_bank, rom_type).await;
+                }
+                Msg::StartPartial {console, offset_kb, length_kb} => {
+                    match console {
+                        MsgStartConsole::Nes => {self.dump_nes_partial(offset_kb, length_kb).await;}
+                        _ => {
+                            self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+                            self.out_channel.send(Msg::End).await;
                         }
-                        _ => {}
-                    }
+                    };
+                }
+                Msg::StartMemory {memory} => {
+                    match memory {
+                        MsgStartMemory::NesSram => {self.dump_nes_sram().await;}
+                        MsgStartMemory::NesPrgRam => {self.dump_nes_prg_ram().await;}
+                        MsgStartMemory::GbSram => {self.dump_gb_sram().await;}
+                        MsgStartMemory::Vrc7Patches => {self.dump_vrc7_patch_rom().await;}
+                        MsgStartMemory::Namco163Ram => {self.dump_namco163_ram().await;}
+                        MsgStartMemory::GbaEeprom => {self.dump_gba_eeprom().await;}
+                        MsgStartMemory::BandaiEeprom => {self.dump_bandai_eeprom().await;}
+                        MsgStartMemory::GbaFlash => {self.dump_gba_flash_save().await;}
+                        MsgStartMemory::Mmc3Wram => {self.dump_mmc3_wram().await;}
+                        MsgStartMemory::Mmc5ExRam => {self.dump_mmc5_exram().await;}
+                    };
+                }
+                Msg::WriteSram { data, length, offset } => {
+                    self.write_sram(offset, &data[..length]).await;
+                }
+                Msg::WriteSramEnd => {
+                    self.end_sram_access().await;
+                    self.out_channel.send(Msg::End).await;
+                }
+                Msg::Patch { address, value } => {
+                    self.write_patch(address, value).await;
+                }
+                Msg::Reconfigure { config } => {
+                    self.config = config;
                 }
                 _ => {}
             }
         }
     }
 
-    async fn dump_nes(&mut self) {
+    /// Puts every bus signal back to its power-on state: data lines as pulled up inputs, address
+    /// lines at 0 and the control strobes deasserted.
+    fn reset_to_idle(&mut self) {
         for dpin in &mut self.d {
             dpin.set_as_input(Pull::Up);
         }
+        self.set_d_snes_pullup();
         self.ciram_ce.set_as_input(Pull::Up);
         self.irq.set_as_input(Pull::Up);
-        self.out_channel.send(Msg::DumpSetupData{ rom_size:
-            ((self.config.prg as u32 + self.config.chr as u32) * 1024) + 16
-            }).await;
-
-        // 16 byte header
-        self.buffer[..4].copy_from_slice(&[0x4Eu8, 0x45u8, 0x53u8, 0x1Au8]);
-        self.buffer[4] = (self.config.prg / 16) as u8;
-        self.buffer[5] = (self.config.chr / 8) as u8;
-        self.buffer[6] = (self.config.mapper & 0xF) << 4;
-        self.buffer[7..16].copy_from_slice(&[0x00u8; 9]);
-        self.out_channel.send(Msg::Data { data: *self.buffer, length: 16 }).await;
-
-        self.read_prg(self.config.mapper, self.config.prgsize).await;
-        if self.config.chrsize > 0 {
-            self.read_chr(self.config.mapper, self.config.chrsize).await;
-        }
-        self.out_channel.send(Msg::End).await;
+        self.set_address(0);
+        self.a15.set_high();
+        self.set_phy2_high();
+        self.set_romsel_high();
+        self.set_prg_read();
+        self.chr_wr.set_high();
+        self.set_chr_read_high();
+        self.set_reset_high();
+        self.set_cs_high();
+        self.set_wr_high();
+        self.set_rd_high();
+        self.set_refresh_high();
+        self.current_mode = ConsoleMode::Idle;
     }
 
-    async fn read_prg(&mut self, mapper: u8, size: u8) {
-        self.set_address(0);
+    /// Sets up every pin for the NES connector, starting from [`Self::reset_to_idle`].
+    fn configure_for_nes(&mut self) {
+        self.reset_to_idle();
+        for dpin in &mut self.d {
+            dpin.set_as_input(Pull::Up);
+        }
+        self.ciram_ce.set_as_input(Pull::Up);
+        self.irq.set_as_input(Pull::Up);
+        self.current_mode = ConsoleMode::Nes;
+    }
+
+    /// Maps the battery backed PRG-RAM at $6000-$7FFF, enabling it on boards that gate it.
+    async fn begin_sram_access(&mut self) {
+        self.configure_for_nes();
+        match self.config.mapper {
+            1 => {
+                self.write_prg_byte(0x8000, 0x80).await;
+                self.write_mmc1_byte(0xE000, 0x00).await;  // Bit 4 clear: PRG-RAM enabled
+            }
+            4 | 47 => {
+                self.write_prg_byte(0xA001, 0x80).await;  // PRG RAM Chip Enable, Writable
+            }
+            _ => {}
+        }
+    }
+
+    async fn end_sram_access(&mut self) {
+        if matches!(self.config.mapper, 4 | 47) {
+            self.write_prg_byte(0xA001, 0x40).await;  // PRG RAM Chip Enable, Write protected
+        }
+        self.set_mode_read();
+        self.set_address(0);
+        self.set_phy2_high();
+        self.set_romsel_high();
+    }
+
+    /// Dumps the 8 KB of SRAM at $6000-$7FFF.
+    async fn dump_nes_sram(&mut self) {
+        if self.config.mapper == 19 {
+            self.dump_namco163_sram().await;
+            return;
+        }
+        self.begin_sram_access().await;
+        if self.config.mapper == 4 {
+            self.cartridge_info.sram_write_protected = Some(self.config.sram_write_protect);
+            self.out_channel.send(Msg::CartridgeInfo { info: self.cartridge_info }).await;
+        }
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: 0x2000 }).await;
+        for address in (0x0000..0x2000u16).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in 0..self.buffer.len() {
+                self.buffer[x] = self.read_prg_byte(0x6000 + address + x as u16).await;
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.end_sram_access().await;
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Dumps the PRG-RAM at $6000-$7FFF, going through the FME-7 RAM banking on mapper 69.
+    async fn dump_nes_prg_ram(&mut self) {
+        if self.config.mapper != 69 {
+            self.dump_nes_sram().await;
+            return;
+        }
+        self.configure_for_nes();
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: 0x2000 }).await;
+        // Battery backed boards carry a single 8 KB chip, further banks would only mirror it
+        self.dump_fme7_sram(1).await;
+        self.end_sram_access().await;
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Dumps the 8 KB of MMC3 work RAM at $6000-$7FFF, mapped with $A001 = 0x80 and write
+    /// protected again afterwards. The RAM has no battery: what comes out is whatever it held,
+    /// random after power-on, not content that belongs to the cartridge. Boards without
+    /// `has_wram` get an empty object.
+    async fn dump_mmc3_wram(&mut self) {
+        if !self.config.has_wram || !matches!(self.config.mapper, 4 | 118 | 119) {
+            self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+            self.out_channel.send(Msg::End).await;
+            return;
+        }
+        self.configure_for_nes();
+        self.write_prg_byte(0xA001, 0x80).await;  // PRG RAM Chip Enable, Writable
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: 0x2000 }).await;
+        for address in (0x6000..0x8000u16).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in 0..self.buffer.len() {
+                self.buffer[x] = self.read_prg_byte(address + x as u16).await;
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.write_prg_byte(0xA001, 0x40).await;  // PRG RAM Chip Enable, Write protected
+        self.end_sram_access().await;
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Dumps the 1 KB of MMC5 ExRAM at $5C00-$5FFF, switched to mode 3 ($5104 = 3) where the
+    /// CPU reads it. Like the other internal RAMs it holds what the chip was last given, or
+    /// random data after power-on. Other mappers get an empty object.
+    async fn dump_mmc5_exram(&mut self) {
+        if self.config.mapper != 5 {
+            self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+            self.out_channel.send(Msg::End).await;
+            return;
+        }
+        self.configure_for_nes();
+        self.write_prg_byte(0x5104, 0x03).await;  // ExRAM mode 3: read-only RAM
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: 0x400 }).await;
+        for address in (0x5C00..0x6000u16).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in 0..self.buffer.len() {
+                self.buffer[x] = self.read_prg_byte(address + x as u16).await;
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.end_sram_access().await;
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Maps `banks` 8 KB FME-7 RAM banks in turn at $6000-$7FFF through command 8 (bit 7 RAM
+    /// enable, bit 6 RAM instead of ROM) and dumps them, then maps PRG-ROM bank 0 back.
+    async fn dump_fme7_sram(&mut self, banks: u8) {
+        for bank in 0..banks.min(8) {
+            self.write_prg_byte(0x8000, 0x08).await;
+            self.write_prg_byte(0xA000, 0xC0 | bank).await;
+            for address in (0x6000..0x8000u16).step_by(Msg::DATA_CHANNEL_SIZE) {
+                for x in 0..self.buffer.len() {
+                    self.buffer[x] = self.read_prg_byte(address + x as u16).await;
+                }
+                self.send_buffer(self.buffer.len()).await;
+            }
+        }
+        self.write_prg_byte(0x8000, 0x08).await;
+        self.write_prg_byte(0xA000, 0x00).await;
+    }
+
+    /// Tries to read the 16 x 8 byte instrument patch ROM of a VRC7 through its audio ports,
+    /// selecting a patch byte at $9010 and reading it back at $9030. Genuine chips treat both ports
+    /// as write-only, only some clones answer: when the bus reads all 0s or all 1s the object is
+    /// left empty.
+    async fn dump_vrc7_patch_rom(&mut self) {
+        self.configure_for_nes();
+        let mut patches = [0u8; VRC7_PATCH_ROM_SIZE];
+        for (index, byte) in patches.iter_mut().enumerate() {
+            self.write_prg_byte(0x9010, index as u8).await;
+            *byte = self.read_prg_byte(0x9030).await;
+        }
+        if patches.iter().all(|byte| *byte == 0x00) || patches.iter().all(|byte| *byte == 0xFF) {
+            self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+            self.out_channel.send(Msg::End).await;
+            return;
+        }
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: VRC7_PATCH_ROM_SIZE as u32 }).await;
+        for chunk in patches.chunks(Msg::DATA_CHANNEL_SIZE) {
+            self.buffer[..chunk.len()].copy_from_slice(chunk);
+            self.send_buffer(chunk.len()).await;
+        }
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Dumps the 8 KB of battery backed RAM of a Namco 163 board at $6000-$7FFF, enabled with
+    /// bit 7 of $E001 and disabled again afterwards. Boards without `has_battery` get an empty
+    /// object, there is no save to back up.
+    async fn dump_namco163_sram(&mut self) {
+        if !self.config.has_battery {
+            self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+            self.out_channel.send(Msg::End).await;
+            return;
+        }
+        self.configure_for_nes();
+        self.write_prg_byte(0xE001, 0x80).await;
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: 0x2000 }).await;
+        for address in (0x6000..0x8000u16).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in 0..self.buffer.len() {
+                self.buffer[x] = self.read_prg_byte(address + x as u16).await;
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.write_prg_byte(0xE001, 0x00).await;
+        self.end_sram_access().await;
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Dumps the 128 bytes of Namco 163 internal RAM, selecting each address at $F800 (bit 7
+    /// clear, no auto-increment) and reading it at $4800. The RAM holds the sound channel state
+    /// and waveforms, so this is whatever the chip powered up with, not a fixed content.
+    async fn dump_namco163_ram(&mut self) {
+        self.configure_for_nes();
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: NAMCO163_RAM_SIZE as u32 }).await;
+        for address in (0..NAMCO163_RAM_SIZE as u8).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in 0..self.buffer.len() {
+                self.write_prg_byte(0xF800, address + x as u8).await;
+                self.buffer[x] = self.read_prg_byte(0x4800).await;
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Dumps the serial EEPROM of a Bandai FCG board, bit-banging I2C through
+    /// [`BANDAI_EEPROM_CONTROL`]. The 24C02 gets a dummy write of word address 0 before the
+    /// sequential read; the X24C01 takes its 7 bit word address right after the start condition
+    /// and sends every byte LSB first. With no EEPROM configured the object is left empty.
+    async fn dump_bandai_eeprom(&mut self) {
+        let (size, lsb_first) = match self.config.bandai_eeprom_type {
+            1 => (256, false),
+            2 => (128, true),
+            _ => {
+                self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+                self.out_channel.send(Msg::End).await;
+                return;
+            }
+        };
+        self.configure_for_nes();
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: size as u32 }).await;
+        self.bandai_eeprom_start().await;
+        let acknowledged = if lsb_first {
+            // Word address 0 in bits 6:0, read in bit 7
+            self.bandai_eeprom_send(0x80, true).await
+        } else {
+            self.bandai_eeprom_send(0xA0, false).await;
+            self.bandai_eeprom_send(0x00, false).await;
+            self.bandai_eeprom_start().await;
+            self.bandai_eeprom_send(0xA1, false).await
+        };
+        if !acknowledged {
+            self.out_channel.send(Msg::Warning { code: Msg::WARNING_EEPROM_NO_ACK }).await;
+        }
+        for address in (0..size).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in 0..self.buffer.len() {
+                let last = address + x == size - 1;
+                self.buffer[x] = self.bandai_eeprom_receive(lsb_first, !last).await;
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.bandai_eeprom_stop().await;
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Sets the EEPROM lines, `sda: None` releasing SDA to the EEPROM.
+    async fn bandai_eeprom_lines(&mut self, scl: bool, sda: Option<bool>) {
+        let value = match sda {
+            Some(sda) => ((scl as u8) << 5) | ((sda as u8) << 6),
+            None => ((scl as u8) << 5) | 0xC0,
+        };
+        self.write_prg_byte(BANDAI_EEPROM_CONTROL, value).await;
+        self.wait_ns(BANDAI_EEPROM_HALF_PERIOD_NS).await;
+    }
+
+    /// SDA falling while SCL is high.
+    async fn bandai_eeprom_start(&mut self) {
+        self.bandai_eeprom_lines(false, Some(true)).await;
+        self.bandai_eeprom_lines(true, Some(true)).await;
+        self.bandai_eeprom_lines(true, Some(false)).await;
+        self.bandai_eeprom_lines(false, Some(false)).await;
+    }
+
+    /// SDA rising while SCL is high.
+    async fn bandai_eeprom_stop(&mut self) {
+        self.bandai_eeprom_lines(false, Some(false)).await;
+        self.bandai_eeprom_lines(true, Some(false)).await;
+        self.bandai_eeprom_lines(true, Some(true)).await;
+    }
+
+    /// Clocks `byte` out and then the acknowledge bit in, returning whether the EEPROM pulled
+    /// SDA low.
+    async fn bandai_eeprom_send(&mut self, byte: u8, lsb_first: bool) -> bool {
+        for bit in 0..8 {
+            let level = (if lsb_first { byte >> bit } else { byte >> (7 - bit) }) & 1 != 0;
+            self.bandai_eeprom_lines(false, Some(level)).await;
+            self.bandai_eeprom_lines(true, Some(level)).await;
+            self.bandai_eeprom_lines(false, Some(level)).await;
+        }
+        self.bandai_eeprom_lines(false, None).await;
+        self.bandai_eeprom_lines(true, None).await;
+        let ack = self.read_prg_byte(BANDAI_EEPROM_DATA).await & 0x10 == 0;
+        self.bandai_eeprom_lines(false, None).await;
+        ack
+    }
+
+    /// Clocks a byte in, then acknowledges it to continue the sequential read or leaves SDA
+    /// high on the last one.
+    async fn bandai_eeprom_receive(&mut self, lsb_first: bool, ack: bool) -> u8 {
+        let mut byte = 0u8;
+        for bit in 0..8 {
+            self.bandai_eeprom_lines(true, None).await;
+            let level = (self.read_prg_byte(BANDAI_EEPROM_DATA).await >> 4) & 1;
+            byte |= if lsb_first { level << bit } else { level << (7 - bit) };
+            self.bandai_eeprom_lines(false, None).await;
+        }
+        self.bandai_eeprom_lines(false, Some(!ack)).await;
+        self.bandai_eeprom_lines(true, Some(!ack)).await;
+        self.bandai_eeprom_lines(false, Some(!ack)).await;
+        byte
+    }
+
+    /// Writes `data` into SRAM starting at `$6000 + offset`. Chunks arrive in order, the first
+    /// one maps the SRAM and [`Msg::WriteSramEnd`] write protects it again.
+    /// When the MMC3 SRAM is configured as write protected, the restore is refused with
+    /// [`Msg::ERROR_SRAM_WRITE_PROTECTED`] unless `force_write` is configured. $A001 is write
+    /// only, so the state comes from `sram_write_protect` rather than from probing the save.
+    async fn write_sram(&mut self, offset: u16, data: &[u8]) {
+        if offset == 0 {
+            self.begin_sram_access().await;
+            self.sram_write_blocked = false;
+            if self.config.mapper == 4 {
+                let protected = self.config.sram_write_protect;
+                self.cartridge_info.sram_write_protected = Some(protected);
+                if protected && !self.config.force_write {
+                    self.sram_write_blocked = true;
+                    self.out_channel.send(Msg::Error { code: Msg::ERROR_SRAM_WRITE_PROTECTED, context: 0 }).await;
+                }
+            }
+        }
+        if self.sram_write_blocked {
+            return;
+        }
+        for (x, byte) in data.iter().enumerate() {
+            let address = 0x6000 + ((offset as usize + x) & 0x1FFF) as u16;
+            self.write_prg_byte(address, *byte).await;
+        }
+        crate::kick_watchdog();
+    }
+
+    /// Writes a single PRG address through the register interface of the configured mapper.
+    async fn write_patch(&mut self, address: u16, value: u8) {
+        match self.config.mapper {
+            0 | 4 => {
+                self.write_prg_byte(address, value).await;
+            }
+            1 => {
+                self.write_mmc1_byte(address, value).await;
+            }
+            _ => {}
+        }
+    }
+
+    async fn dump_nes(&mut self, format: NesOutputFormat) {
+        if !matches!(self.current_mode, ConsoleMode::Idle | ConsoleMode::Nes) {
+            self.out_channel.send(Msg::Warning { code: Msg::WARNING_BUS_MODE_SWITCH }).await;
+        }
+        self.configure_for_nes();
+        if self.config.mapper == 20 {
+            self.dump_fds_cart().await;
+            return;
+        }
+        if !self.config.skip_bus_check {
+            if let Some(code) = self.check_nes_bus().await {
+                self.out_channel.send(Msg::Error { code, context: 0 }).await;
+                self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+                self.out_channel.send(Msg::End).await;
+                return;
+            }
+        }
+        let has_header = matches!(format, NesOutputFormat::INes1 | NesOutputFormat::INes2);
+        let with_chr = format != NesOutputFormat::RawPrg;
+        // CHR size of this dump only, the configuration keeps what the host set
+        let mut chr_kb = self.config.chr;
+        let mut chrsize = self.config.chrsize;
+        if self.config.auto_detect_chr && with_chr && chrsize > 0 {
+            let detected_chr_kb = self.detect_chr_mirror().await;
+            self.cartridge_info.detected_chr_kb = Some(detected_chr_kb);
+            // iNES counts CHR-ROM in 8 KB pages: a 4 KB NROM CHR is only reported, and still
+            // dumped as the 8 KB the header can describe
+            if detected_chr_kb < chr_kb && detected_chr_kb % 8 == 0 {
+                chr_kb = detected_chr_kb;
+                chrsize = (detected_chr_kb / 4).trailing_zeros() as u8;
+            }
+        }
+        if self.exceeds_mapper_banks(chrsize, with_chr) {
+            self.out_channel.send(Msg::Error { code: Msg::ERROR_BANK_OVERFLOW, context: self.config.mapper as u32 }).await;
+            self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+            self.out_channel.send(Msg::End).await;
+            return;
+        }
+        let trainer_addr = if has_header { self.config.trainer_addr } else { None };
+        self.out_channel.send(Msg::DumpSetupData{ rom_size:
+            ((self.config.prg as u32 + if with_chr { chr_kb as u32 } else { 0 }) * 1024) +
+            (if has_header { 16 } else { 0 }) +
+            if trainer_addr.is_some() { NES_TRAINER_SIZE as u32 } else { 0 }
+            }).await;
+
+        if has_header {
+            let is_vs_system = self.config.is_vs_system ||
+                (self.config.detect_vs_system && self.detect_vs_system().await);
+            let nes2 = format == NesOutputFormat::INes2 || self.config.ines_version == 2;
+            self.fill_nes_header(is_vs_system, nes2, chr_kb);
+            self.send_buffer(16).await;
+        }
+        if let Some(trainer_addr) = trainer_addr {
+            self.dump_nes_trainer(trainer_addr).await;
+        }
+
+        self.cartridge_info.best_guess_mapper = self.detect_nes_mapper().await;
+        self.cartridge_info.standard_pinout = Some(self.detect_pinout_standard().await);
+        // A floating EXP line cannot tell the slots apart, the build is described by the config
+        self.cartridge_info.slot_type = Some(self.config.slot_type);
+        // Smaller NROM boards leave A14 unconnected and would always fail
+        if self.config.mapper == 0 && self.config.prg == 32 {
+            self.cartridge_info.address_bus = Some(self.test_address_bus().await);
+        }
+        let read_latency_ns = self.measure_read_latency().await;
+        self.cartridge_info.read_latency_ns = Some(read_latency_ns);
+        if self.config.auto_read_delay {
+            self.config.read_delay_ns = read_latency_ns;
+        }
+
+        self.prg_crc32 = 0xFFFFFFFF;
+        self.chr_crc32 = 0xFFFFFFFF;
+        self.read_prg(self.config.mapper, self.config.prgsize).await;
+        if with_chr && chrsize > 0 {
+            self.invert_chr = self.config.invert_chr;
+            self.read_chr(self.config.mapper, chrsize).await;
+            self.invert_chr = false;
+        }
+        let chr_crc32 = if with_chr && chrsize > 0 { !self.chr_crc32 } else { 0 };
+        self.out_channel.send(Msg::Checksum { prg_crc32: !self.prg_crc32, chr_crc32 }).await;
+        if self.config.verify && with_chr {
+            self.check_known_crc(chr_kb).await;
+        }
+        self.out_channel.send(Msg::CartridgeInfo { info: self.cartridge_info }).await;
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Dumps one game of a Camerica Quattro cartridge, the 16 KB banks from `offset_kb` to
+    /// `offset_kb + length_kb`, behind a header of its own. Within its 64 KB block a game sees a
+    /// switchable bank at $8000 and its last bank at $C000, so the image is written as UxROM
+    /// (mapper 2) with CHR-RAM. Other mappers have no partial dump and get an empty object.
+    async fn dump_nes_partial(&mut self, offset_kb: u16, length_kb: u16) {
+        self.configure_for_nes();
+        if self.config.mapper != 232 || length_kb == 0 || offset_kb + length_kb > self.config.prg {
+            self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+            self.out_channel.send(Msg::End).await;
+            return;
+        }
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: length_kb as u32 * 1024 + 16 }).await;
+        self.buffer[..16].copy_from_slice(&[0u8; 16]);
+        self.buffer[..4].copy_from_slice(&[0x4Eu8, 0x45u8, 0x53u8, 0x1Au8]);
+        self.buffer[4] = (length_kb / 16) as u8;
+        self.buffer[6] = 0x20;
+        self.send_buffer(16).await;
+        self.prg_crc32 = 0xFFFFFFFF;
+        self.dump_quattro_prg_banks(offset_kb / 16, length_kb / 16).await;
+        self.set_address(0);
+        self.set_phy2_high();
+        self.set_romsel_high();
+        self.out_channel.send(Msg::Checksum { prg_crc32: !self.prg_crc32, chr_crc32: 0 }).await;
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Dumps `banks16` 16 KB of MMC5 PRG-ROM in the configured `$5100` mode, through the
+    /// window each mode switches: $5117 (32 KB) in mode 0, $5115 (16 KB at $8000) in modes 1 and
+    /// 2, $5114 (8 KB at $8000) in mode 3. Bank numbers count 8 KB and bit 7 selects ROM over
+    /// RAM. Outside mode 0, $E000-$FFFF keeps the last bank through $5117.
+    async fn dump_mmc5_prg(&mut self, banks16: u16) {
+        let mode = self.config.mmc5_prg_mode & 0x03;
+        self.write_prg_byte(0x5100, mode).await;
+        if mode != 0 {
+            self.write_prg_byte(0x5117, 0xFF).await;
+        }
+        match mode {
+            0 => {
+                for bank in 0..banks16.div_ceil(2) {
+                    self.write_prg_byte(0x5117, (bank << 2) as u8).await;
+                    self.dump_bank_prg(0x0, 0x8000, 0x8000).await;
+                }
+            }
+            1 | 2 => {
+                for bank in 0..banks16 {
+                    self.write_prg_byte(0x5115, 0x80 | (bank << 1) as u8).await;
+                    self.dump_bank_prg(0x0, 0x4000, 0x8000).await;
+                }
+            }
+            _ => {
+                for bank in 0..banks16 * 2 {
+                    self.write_prg_byte(0x5114, 0x80 | bank as u8).await;
+                    self.dump_bank_prg(0x0, 0x2000, 0x8000).await;
+                }
+            }
+        }
+    }
+
+    /// Dumps `count` 16 KB mapper 232 banks from `first` at $8000-$BFFF: bits 4:3 of $8000 pick
+    /// the 64 KB block, bits 1:0 of $C000 the bank inside it.
+    async fn dump_quattro_prg_banks(&mut self, first: u16, count: u16) {
+        for bank in first..first + count {
+            self.write_prg_byte(0x8000, ((bank >> 2) << 3) as u8).await;
+            self.write_prg_byte(0xC000, (bank & 0x03) as u8).await;
+            self.dump_bank_prg(0x0, 0x4000, 0x8000).await;
+        }
+    }
+
+    /// Looks the PRG and CHR CRCs of the dump just made, with `chr_kb` of CHR-ROM, up in
+    /// [`crate::mapper_crc`]. Nothing is reported when the table does not know the mapper and
+    /// sizes.
+    async fn check_known_crc(&mut self, chr_kb: u16) {
+        let chr_crc32 = if self.config.chrsize > 0 { !self.chr_crc32 } else { 0 };
+        let actual_crc = (!self.prg_crc32, chr_crc32);
+        let chr_kb = if self.config.chrsize > 0 { chr_kb } else { 0 };
+        if let Some((passed, expected_crc)) = crate::mapper_crc::check(self.config.mapper, self.config.prg, chr_kb, actual_crc.0, actual_crc.1) {
+            self.out_channel.send(Msg::CrcCheck { passed, expected_crc, actual_crc }).await;
+        }
+    }
+
+    /// Reads a few PRG addresses with /ROMSEL asserted and then deasserted. On a standard board
+    /// the ROM lets go of the bus in the second case; if every byte reads back the same, the ROM
+    /// ignores /ROMSEL and A15 is wired differently.
+    async fn detect_pinout_standard(&mut self) -> bool {
+        let mut answered_unselected = 0;
+        for address in NES_BUS_CHECK_ADDRESSES {
+            let selected = self.read_prg_byte(address).await;
+            self.set_romsel_high();
+            Timer::after_micros(1).await;
+            let unselected = self.read_data();
+            if selected == unselected && !Self::is_open_bus(unselected) {
+                answered_unselected += 1;
+            }
+        }
+        answered_unselected < NES_BUS_CHECK_ADDRESSES.len()
+    }
+
+    /// A data line nobody drives floats high through its pull-up.
+    fn is_open_bus(data: u8) -> bool {
+        data == 0xFF
+    }
+
+    /// Looks for a data bus stuck high (open bus) or low before dumping, which usually means a
+    /// missing cartridge or dirty contacts. Returns the error code to report, if any.
+    async fn check_nes_bus(&mut self) -> Option<u8> {
+        let mut high = 0;
+        let mut low = 0;
+        for address in NES_BUS_CHECK_ADDRESSES {
+            let data = self.read_prg_byte(address).await;
+            if Self::is_open_bus(data) {
+                high += 1;
+            } else if data == 0x00 {
+                low += 1;
+            }
+        }
+        if high >= NES_BUS_CHECK_THRESHOLD {
+            Some(Msg::ERROR_BUS_STUCK_HIGH)
+        } else if low >= NES_BUS_CHECK_THRESHOLD {
+            Some(Msg::ERROR_BUS_STUCK_LOW)
+        } else {
+            None
+        }
+    }
+
+    /// Finds out how much of the configured CHR-ROM is not a mirror, comparing CRCs of whole
+    /// windows so that a tile shared by several banks does not pass for a mirror. On NROM the 4 KB
+    /// halves at $0000 and $1000 are compared; on MMC3 the first and last 1 KB banks of each half
+    /// of the CHR-ROM, halving while both pairs match. Other mappers report the configured size.
+    async fn detect_chr_mirror(&mut self) -> u16 {
+        match self.config.mapper {
+            0 => {
+                let low = self.chr_window_crc(0x0000, 0x1000).await;
+                let high = self.chr_window_crc(0x1000, 0x1000).await;
+                if low == high { 4 } else { self.config.chr }
+            }
+            4 | 118 | 119 => {
+                let mut chr_kb = self.config.chr;
+                while chr_kb > 8 {
+                    let half = chr_kb / 2;
+                    let first_match = self.mmc3_chr_bank_crc(0).await == self.mmc3_chr_bank_crc(half).await;
+                    if !first_match || self.mmc3_chr_bank_crc(half - 1).await != self.mmc3_chr_bank_crc(chr_kb - 1).await {
+                        break;
+                    }
+                    chr_kb = half;
+                }
+                chr_kb
+            }
+            _ => self.config.chr,
+        }
+    }
+
+    /// CRC of `length` CHR bytes from PPU address `from`, read without being sent.
+    async fn chr_window_crc(&mut self, from: u16, length: u16) -> u32 {
+        let mut crc = 0xFFFFFFFF;
+        for address in (from..from + length).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in 0..self.buffer.len() {
+                self.buffer[x] = self.read_chr_byte(address + x as u16).await;
+            }
+            crc = Self::crc32_update(crc, &self.buffer[..]);
+        }
+        !crc
+    }
+
+    /// CRC of 1 KB CHR bank `bank`, mapped at $1000 through R2.
+    async fn mmc3_chr_bank_crc(&mut self, bank: u16) -> u32 {
+        self.write_prg_byte(0x8000, 0x02).await;
+        self.write_prg_byte(0x8001, bank as u8).await;
+        self.chr_window_crc(0x1000, CHR_MIRROR_CHECK_SIZE).await
+    }
+
+    /// Walks a one through A0-A14 of a 32 KB NROM cartridge, comparing each byte with the one
+    /// at the base address and with the bytes of the other lines. ROM data is not known in
+    /// advance, so this only sees a line that changes nothing, or two lines that read alike: the
+    /// signature of a broken or crossed wire. A clean swap of two lines that both reach the ROM
+    /// reads different, valid looking bytes and needs a known CRC to be caught.
+    async fn test_address_bus(&mut self) -> BusTestResult {
+        let mut suspect_lines = 0x7FFFu16;
+        let mut suspect_pairs = [0x7FFFu16; 15];
+        for base in ADDRESS_BUS_TEST_BASES {
+            let reference = self.read_prg_byte(0x8000 | base).await;
+            let mut walked = [0u8; 15];
+            for (bit, data) in walked.iter_mut().enumerate() {
+                *data = self.read_prg_byte(0x8000 | (base ^ (1 << bit))).await;
+            }
+            for bit in 0..walked.len() {
+                if walked[bit] != reference {
+                    suspect_lines &= !(1 << bit);
+                }
+                for other in 0..walked.len() {
+                    if other == bit || walked[other] != walked[bit] {
+                        suspect_pairs[bit] &= !(1 << other);
+                    }
+                }
+            }
+        }
+        self.set_romsel_high();
+
+        let mut swapped_bits = suspect_lines;
+        for (bit, pairs) in suspect_pairs.iter().enumerate() {
+            if *pairs != 0 {
+                swapped_bits |= (1 << bit) | pairs;
+            }
+        }
+        BusTestResult { swapped_bits }
+    }
+
+    /// Copies the 512 byte trainer at `trainer_addr`, which iNES stores between the header and
+    /// PRG-ROM. It is left out of the PRG checksum.
+    async fn dump_nes_trainer(&mut self, trainer_addr: u16) {
+        for address in (0..NES_TRAINER_SIZE as u16).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in 0..self.buffer.len() {
+                self.buffer[x] = self.read_prg_byte(trainer_addr.wrapping_add(address + x as u16)).await;
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+    }
+
+    /// Guesses the mapper from the start of PRG-ROM and the interrupt vectors. Banked mappers keep
+    /// the reset code in the fixed upper bank, so a reset vector below $C000 means a 32 KB
+    /// unbanked PRG-ROM.
+    async fn detect_nes_mapper(&mut self) -> Option<u8> {
+        let mut start = [0u8; 8];
+        for (x, byte) in start.iter_mut().enumerate() {
+            *byte = self.read_prg_byte(0x8000 + x as u16).await;
+        }
+        let mut vectors = [0u8; 8];
+        for (x, byte) in vectors.iter_mut().enumerate() {
+            *byte = self.read_prg_byte(0xFFF8 + x as u16).await;
+        }
+        self.set_romsel_high();
+
+        if let Some((_, mapper)) = NES_MAPPER_SIGNATURES.iter().find(|(signature, _)| start.starts_with(signature)) {
+            return Some(*mapper);
+        }
+        let reset_vector = u16::from_le_bytes([vectors[4], vectors[5]]);
+        if (0x8000..0xC000).contains(&reset_vector) {
+            return Some(0);
+        }
+        None
+    }
+
+    /// Writes the 16 byte iNES header in the first bytes of the buffer for `chr_kb` of CHR-ROM,
+    /// with the NES 2.0 fields when `nes2` is set.
+    fn fill_nes_header(&mut self, is_vs_system: bool, nes2: bool, chr_kb: u16) {
+        self.buffer[..4].copy_from_slice(&[0x4Eu8, 0x45u8, 0x53u8, 0x1Au8]);
+        self.buffer[4] = (self.config.prg / 16) as u8;
+        // No CHR pages means CHR-RAM
+        self.buffer[5] = if self.config.chrsize == 0 { 0 } else { (chr_kb / 8) as u8 };
+        self.buffer[6] = (self.config.mapper & 0xF) << 4;
+        if self.config.trainer_addr.is_some() {
+            self.buffer[6] |= 0x04;
+        }
+        self.buffer[7..16].copy_from_slice(&[0x00u8; 9]);
+        if nes2 {
+            // NES 2.0 identifier, console type 1 = Vs. System
+            self.buffer[7] = (self.config.mapper & 0xF0) | 0x08 | (is_vs_system as u8);
+            self.buffer[8] = self.config.submapper << 4;
+            let prg_ram_kb = match self.config.prg_ram_kb {
+                Some(kb) => kb,
+                None if self.config.mapper == 4 => 8,
+                None => 0,
+            };
+            self.buffer[10] = (Self::nes2_ram_shift(self.config.prg_nvram_kb) << 4) | Self::nes2_ram_shift(prg_ram_kb);
+            let chr_ram_kb = if self.config.chrsize == 0 { self.config.chr_ram_kb } else { 0 };
+            self.buffer[11] = (Self::nes2_ram_shift(self.config.chr_nvram_kb) << 4) | Self::nes2_ram_shift(chr_ram_kb);
+            if is_vs_system {
+                // Vs. hardware byte: hardware type in bits 7:4, PPU type in bits 3:0
+                self.buffer[13] = ((self.config.vs_hardware & 0x0F) << 4) | (self.config.vs_palette & 0x0F);
+            }
+        }
+    }
+
+    /// NES 2.0 RAM size nibble for `kb` kilobytes: the size is 64 << shift bytes, 0 meaning none.
+    fn nes2_ram_shift(kb: u16) -> u8 {
+        match kb {
+            0 => 0,
+            kb => ((kb as u32 * 1024).ilog2() - 6) as u8 & 0x0F,
+        }
+    }
+
+    /// Dumps an FDS backup card as a `.fds` image.
+    ///
+    /// The card exposes 4 KB pages through the FDS expansion ROM window at `$6000–$DFFF`; each disk
+    /// side spans 16 sequential pages, selected 32 KB at a time through [`FDS_PAGE_LATCH`], and is
+    /// truncated to the 65500 bytes of a disk side.
+    async fn dump_fds_cart(&mut self) {
+        let sides = self.config.fds_sides.clamp(1, 2);
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: sides as u32 * FDS_SIDE_SIZE + 16 }).await;
+
+        self.buffer[..4].copy_from_slice(&[0x46u8, 0x44u8, 0x53u8, 0x1Au8]);
+        self.buffer[4] = sides;
+        self.buffer[5..16].copy_from_slice(&[0x00u8; 11]);
+        self.send_buffer(16).await;
+
+        self.prg_crc32 = 0xFFFFFFFF;
+        for side in 0..sides {
+            let mut offset: u32 = 0;
+            while offset < FDS_SIDE_SIZE {
+                if offset % 0x8000 == 0 {
+                    self.write_prg_byte(FDS_PAGE_LATCH, side * 2 + (offset / 0x8000) as u8).await;
+                }
+                let length = core::cmp::min(self.buffer.len() as u32, FDS_SIDE_SIZE - offset) as usize;
+                let address = 0x6000 + (offset % 0x8000) as u16;
+                for x in 0..length {
+                    self.buffer[x] = self.read_prg_byte(address + x as u16).await;
+                }
+                self.prg_crc32 = Self::crc32_update(self.prg_crc32, &self.buffer[..length]);
+                self.send_buffer(length).await;
+                offset += length as u32;
+            }
+        }
+        self.out_channel.send(Msg::Checksum { prg_crc32: !self.prg_crc32, chr_crc32: 0 }).await;
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Reads $FFFC `READ_LATENCY_SAMPLES` times with `delay_ns` as read delay and tells whether
+    /// every read gave `expected`.
+    async fn is_read_delay_stable(&mut self, delay_ns: u16, expected: u8) -> bool {
+        let read_delay_ns = self.config.read_delay_ns;
+        self.config.read_delay_ns = delay_ns;
+        let mut stable = true;
+        for _ in 0..READ_LATENCY_SAMPLES {
+            if self.read_prg_byte(0xFFFC).await != expected {
+                stable = false;
+                break;
+            }
+        }
+        self.config.read_delay_ns = read_delay_ns;
+        stable
+    }
+
+    /// Binary searches the shortest read delay, between 0 and the conservative 1 us, at which
+    /// $FFFC reads back the same as with the 1 us delay. The first try is 100 ns.
+    async fn measure_read_latency(&mut self) -> u16 {
+        let read_delay_ns = self.config.read_delay_ns;
+        self.config.read_delay_ns = READ_LATENCY_MAX_NS;
+        let expected = self.read_prg_byte(0xFFFC).await;
+        self.config.read_delay_ns = read_delay_ns;
+
+        let mut stable = READ_LATENCY_MAX_NS;
+        let mut unstable = 0;
+        let mut delay_ns = 100;
+        while stable - unstable > READ_LATENCY_RESOLUTION_NS {
+            if self.is_read_delay_stable(delay_ns, expected).await {
+                stable = delay_ns;
+            } else {
+                unstable = delay_ns;
+            }
+            delay_ns = (stable + unstable) / 2;
+        }
+        stable
+    }
+
+    /// Vs. System boards pull the expansion connector line high, while standard carts leave it
+    /// to the pull-down.
+    async fn detect_vs_system(&mut self) -> bool {
+        for _ in 0..8 {
+            if self.expand.is_low() {
+                return false;
+            }
+            Timer::after_micros(1).await;
+        }
+        true
+    }
+
+    /// Whether the PRG size, or the CHR size when it is dumped, needs more 8 KB PRG or 1 KB CHR
+    /// banks than the MMC3 or VRC bank registers of the configured mapper can select.
+    fn exceeds_mapper_banks(&self, chrsize: u8, with_chr: bool) -> bool {
+        let (prg_limit, chr_limit) = match self.config.mapper {
+            4 | 118 => (256, 256),
+            // TQROM: bit 6 of the CHR bank registers selects CHR-RAM
+            119 => (256, 64),
+            // 5 bit PRG and 9 bit CHR bank numbers
+            21 | 22 | 23 | 25 => (32, 512),
+            _ => return false,
+        };
+        let prg_banks = (1u64 << self.config.prgsize.min(32)) * 2;
+        let chr_banks = (1u64 << chrsize.min(32)) * 4;
+        prg_banks > prg_limit || (with_chr && chrsize > 0 && chr_banks > chr_limit)
+    }
+
+    async fn read_prg(&mut self, mapper: u8, size: u8) {
+        self.set_address(0);
         Timer::after_micros(1).await;
         let base: u16 = 0x8000;
         let mut finalize = true;
@@ -576,33 +2025,123 @@ impl<'d> DumperClass<'d>
                     self.write_prg_byte(0x8000, 0x80).await;
                     self.dump_bank_prg(0x0000, 0x8000, base).await;
                 } else {
+                    // 512 KB SUROM/SXROM boards take the outer 256 KB half from bit 4 of CHR
+                    // bank 0, the inner 16 KB bank still comes from $E000
                     let banks = 1u8 << size;
-                    for i in 0..banks {
-                        self.write_prg_byte(0x8000, 0x80).await;
-                        self.write_mmc1_byte(0x8000, 0x0C).await;
-                        if size > 4 {
-                            self.write_mmc1_byte(0xA000, 0x0C).await;
+                    let outer_banks = (banks / 16).max(1);
+                    let inner_banks = banks.min(16);
+                    for outer_bank in 0..outer_banks {
+                        for inner_bank in 0..inner_banks {
+                            self.write_prg_byte(0x8000, 0x80).await;
+                            self.write_mmc1_byte(0x8000, 0x0C).await;
+                            if outer_banks > 1 || self.config.mmc1_variant == 1 {
+                                self.write_mmc1_byte(0xA000, outer_bank * 0x10).await;
+                            }
+                            self.write_mmc1_byte(0xE000, inner_bank).await;
+                            self.dump_bank_prg(0x0000, 0x4000, base).await;
                         }
-                        if i > 15 {
-                            self.write_mmc1_byte(0xA000, 0x10).await;
-                        }
-                        self.write_mmc1_byte(0xE000, i).await;
-                        self.dump_bank_prg(0x0000, 0x4000, base).await;
                     }
                 }
             },
-            4 => {
+            4 | 118 | 119 => {
+                // Sizes beyond the 256 banks of R6/R7 are refused by exceeds_mapper_banks
                 let banks = (1u16 << size) * 2;
-                if banks > 256 {
-                    panic!("Address overflow");
-                }
                 self.write_prg_byte(0xA001, 0x80).await;  // Block Register - PRG RAM Chip Enable, Writable
+                self.dump_mmc3_prg_banks(banks).await;
+            },
+            6 if self.config.is_ffe_hack => {
+                // FFE registers sit in the PRG-RAM space, ROMSEL stays high while writing them
+                let banks = (1u16 << size) * 2;
+                for outer_bank in 0..banks.div_ceil(FFE_INNER_BANKS as u16) as u8 {
+                    self.write_prg_byte(0x42FE, outer_bank << 3).await;
+                    for inner_bank in 0..(banks as u8).min(FFE_INNER_BANKS) {
+                        self.write_prg_byte(0x42FE, inner_bank | (outer_bank << 3)).await;
+                        self.dump_bank_prg(0x0, 0x2000, base).await;
+                    }
+                }
+            },
+            13 => {
+                self.dump_bank_prg(0x0, 0x8000, base).await;
+            },
+            33 | 48 => {
+                // TC0690 (48) moves mirroring out of $8000, the PRG registers are otherwise the same
+                let banks = (1u16 << size) * 2;
+                for i in 0..banks {
+                    self.write_prg_byte(0x8000, (i & 0x3F) as u8).await;  // PRG bank at $8000-$9FFF, mirroring bit left clear
+                    self.dump_bank_prg(0x0, 0x2000, base).await;
+                }
+            },
+            47 => {
+                // Two MMC3 games, the outer bank register at $6000 is only writable with PRG-RAM
+                // enabled
+                let banks = (1u16 << size) * 2;
+                self.write_prg_byte(0xA001, 0x80).await;
+                for game in 0..2u8 {
+                    self.write_prg_byte(0x6000, game).await;
+                    self.dump_mmc3_prg_banks(banks / 2).await;
+                }
+            },
+            69 => {
+                let banks = (1u16 << size) * 2;
+                for i in 0..banks {
+                    self.write_prg_byte(0x8000, 0x09).await;  // Command 9: PRG bank at $8000-$9FFF
+                    self.write_prg_byte(0xA000, i as u8).await;
+                    self.dump_bank_prg(0x0, 0x2000, base).await;
+                }
+            },
+            89 => {
+                // Single register at $8000-$FFFF: bits 6:4 select the 16 KB bank at $8000, the
+                // last bank is fixed at $C000
+                let banks = 1u8 << size;
+                for i in 0..banks {
+                    self.write_prg_byte(0x8000, (i << 4) & 0x70).await;
+                    self.dump_bank_prg(0x0, 0x4000, base).await;
+                }
+            },
+            184 => {
+                self.dump_bank_prg(0x0000, 0x8000, base).await;
+            },
+            228 => {
+                // The register is the written address: A12:A11 select one of the 512 KB PRG chips,
+                // A10:A6 the PRG page (A6 ignored in 32 KB mode, A5 low). Action 52 only has chips
+                // 0, 1 and 3, so the unpopulated chip 2 is read last.
+                // Action 52 is known for a first PRG bank that looks corrupted in emulators: that is
+                // how the original cartridge is, not a dump error.
+                const CHIPS: [u16; 4] = [0, 1, 3, 2];
+                let slots = (self.config.prg / 32).min(64);
+                for slot in 0..slots {
+                    let chip = CHIPS[(slot / 16) as usize];
+                    let page = (slot % 16) << 1;
+                    self.write_prg_byte(0x8000 | (chip << 11) | (page << 6), 0x00).await;
+                    self.dump_bank_prg(0x0, 0x8000, base).await;
+                }
+            },
+            5 => {
+                self.dump_mmc5_prg(1u16 << size).await;
+            },
+            9 => {
+                let banks = (1u16 << size) * 2;
                 for i in 0..banks {
-                    self.write_prg_byte(0x8000, 0x06).await;  // PRG Bank 0 ($8000-$9FFF)
-                    self.write_prg_byte(0x8001, i as u8).await;
+                    self.write_prg_byte(0xA000, i as u8).await;  // 8 KB PRG bank at $8000-$9FFF
                     self.dump_bank_prg(0x0, 0x2000, base).await;
                 }
             },
+            232 => {
+                self.dump_quattro_prg_banks(0, 1u16 << size).await;
+            },
+            21 | 22 | 23 | 25 => {
+                let variant = self.vrc_board_variant();
+                let banks = (1u16 << size) * 2;
+                self.write_vrc4_reg(variant, 0x9002, 0x00).await;  // PRG swap mode 0: $8000 switchable
+                // $8000 and $A000 both switch 5 bit bank numbers, so every bank, the two fixed at
+                // $C000/$E000 included, is reached through them, 16 KB at a time. Sizes beyond
+                // the 32 banks they select are refused by exceeds_mapper_banks
+                for i in (0..banks).step_by(2) {
+                    self.write_vrc4_reg(variant, 0x8000, i as u8).await;
+                    self.write_vrc4_reg(variant, 0xA000, (i + 1) as u8).await;
+                    self.dump_bank_prg(0x0, 0x4000, base).await;
+                }
+            },
             _ => {
                 finalize = false
             }
@@ -614,6 +2153,65 @@ impl<'d> DumperClass<'d>
         }
     }
 
+    /// Dumps `banks` 8 KB MMC3 PRG banks through R6, the switchable window at $8000-$9FFF.
+    ///
+    /// The bank select value 0x06 also clears bit 6, PRG mode 0: whatever mode the game left the
+    /// MMC3 in, R6 maps $8000 and not $C000, where mode 1 would put it.
+    async fn dump_mmc3_prg_banks(&mut self, banks: u16) {
+        for i in 0..banks {
+            self.write_prg_byte(0x8000, 0x06).await;  // PRG Bank 0 ($8000-$9FFF), PRG mode 0
+            self.write_prg_byte(0x8001, i as u8).await;
+            self.dump_bank_prg(0x0, 0x2000, 0x8000).await;
+        }
+    }
+
+    /// Dumps `banks` 1 KB MMC3 CHR banks, 8 KB at a time: R0/R1 map the 2 KB windows at
+    /// $0000/$0800 and R2-R5 the 1 KB windows at $1000-$1C00, so the whole pattern table space
+    /// holds consecutive banks and is streamed in iNES order.
+    async fn dump_mmc3_chr_banks(&mut self, banks: u16) {
+        // (bank select command, 1 KB bank offset in the 8 KB block)
+        const REGISTERS: [(u8, u16); 6] = [(0x00, 0), (0x01, 2), (0x02, 4), (0x03, 5), (0x04, 6), (0x05, 7)];
+        for first in (0..banks).step_by(8) {
+            for (register, bank_offset) in REGISTERS {
+                self.write_prg_byte(0x8000, register).await;
+                self.write_prg_byte(0x8001, (first + bank_offset) as u8).await;
+            }
+            self.dump_bank_chr(0x0000, (banks - first).min(8) * 0x400).await;
+        }
+    }
+
+    /// Dumps `banks` 1 KB VRC2/VRC4 CHR banks, 8 KB at a time: the eight page registers, each a
+    /// low nibble at $x000/$x002 and a high nibble at $x001/$x003 from $B000 to $E003, map
+    /// consecutive banks over the whole pattern table space.
+    async fn dump_vrc_chr_banks(&mut self, variant: u8, banks: u16) {
+        // Low nibble register of each 1 KB page, from PPU $0000 up
+        const PAGE_REGISTERS: [u16; 8] = [0xB000, 0xB002, 0xC000, 0xC002, 0xD000, 0xD002, 0xE000, 0xE002];
+        for first in (0..banks).step_by(8) {
+            for (page, register) in PAGE_REGISTERS.into_iter().enumerate() {
+                // VRC2a ignores the lowest bit of the CHR bank number
+                let bank = if variant == 0 { (first + page as u16) << 1 } else { first + page as u16 };
+                self.write_vrc4_reg(variant, register, (bank & 0x0F) as u8).await;
+                self.write_vrc4_reg(variant, register + 1, ((bank >> 4) & 0x1F) as u8).await;
+            }
+            self.dump_bank_chr(0x0000, (banks - first).min(8) * 0x400).await;
+        }
+    }
+
+    /// Dumps MMC5 CHR-ROM in 1 KB mode ($5101 = 3), eight banks at a time through the sprite
+    /// registers $5120-$5127, $5130 holding the bank bits above 8. The vertical split ($5200) is
+    /// turned off so that no fetch is redirected to the split bank.
+    async fn dump_mmc5_chr(&mut self, banks: u16) {
+        self.write_prg_byte(0x5101, 0x03).await;
+        self.write_prg_byte(0x5200, 0x00).await;
+        for first in (0..banks).step_by(8) {
+            self.write_prg_byte(0x5130, (first >> 8) as u8).await;
+            for register in 0..8u16 {
+                self.write_prg_byte(0x5120 + register, (first + register) as u8).await;
+            }
+            self.dump_bank_chr(0x0000, (banks - first).min(8) * 0x400).await;
+        }
+    }
+
     async fn read_chr(&mut self, mapper: u8, size: u8) {
         self.set_address(0);
         Timer::after_micros(1).await;
@@ -621,17 +2219,122 @@ impl<'d> DumperClass<'d>
             0 => {
                 self.dump_bank_chr(0x0, 0x2000).await;
             },
-            4 => {
+            5 => {
                 let banks = (1u16 << size) * 4;
-                if banks > 256 {
-                    panic!("Address overflow");
+                self.dump_mmc5_chr(banks).await;
+            }
+            9 => {
+                // The $0000 window takes its bank from $B000 or $C000 depending on the latch, last
+                // flipped by the PPU fetching tile $FD or $FE. With the same bank in both
+                // registers the latch state does not matter, and the 4 KB banks come out in the
+                // iNES order
+                let banks = 1u16 << size;
+                for i in 0..banks {
+                    self.write_prg_byte(0xB000, i as u8).await;
+                    self.write_prg_byte(0xC000, i as u8).await;
+                    self.dump_bank_chr(0x0000, 0x1000).await;
                 }
+            }
+            4 | 118 => {
+                // TxSROM (118) routes bit 7 of the CHR bank registers to CIRAM A10 instead of a
+                // CHR address line, so its CHR is read like a plain MMC3 one
+                let banks = (1u16 << size) * 4;
+                self.write_prg_byte(0xA001, 0x80).await;
+                self.dump_mmc3_chr_banks(banks).await;
+            }
+            119 => {
+                // TQROM uses bit 6 of the CHR bank registers to pick the 8 KB CHR-RAM instead of
+                // CHR-ROM, so only banks 0-63 reach the ROM: 64 KB at most, no upper half
+                let banks = (1u16 << size) * 4;
                 self.write_prg_byte(0xA001, 0x80).await;
+                self.dump_mmc3_chr_banks(banks).await;
+            }
+            33 | 48 => {
+                // $8002/$8003 select the 2 KB banks at $0000/$0800, $A000-$A003 the 1 KB banks at
+                // $1000-$1C00: the first 2 KB window alone reaches the whole CHR-ROM.
+                // The iNES file stores CHR in bank number order, which is the order this walk
+                // produces whatever PPU window a game maps the banks to.
+                let banks = (1u16 << size) * 2;
+                for i in 0..banks {
+                    self.write_prg_byte(0x8002, i as u8).await;
+                    self.dump_bank_chr(0x0000, 0x0800).await;
+                }
+            }
+            47 => {
+                let banks = (1u16 << size) * 4;
+                self.write_prg_byte(0xA001, 0x80).await;
+                for game in 0..2u8 {
+                    self.write_prg_byte(0x6000, game).await;
+                    self.dump_mmc3_chr_banks(banks / 2).await;
+                }
+            }
+            6 if self.config.is_ffe_hack => {
+                let banks = ((1u8 << size) / 2).max(1);
+                for outer_bank in 0..banks.div_ceil(FFE_INNER_BANKS) {
+                    self.write_prg_byte(0x42FF, outer_bank << 3).await;
+                    for inner_bank in 0..banks.min(FFE_INNER_BANKS) {
+                        self.write_prg_byte(0x42FF, inner_bank | (outer_bank << 3)).await;
+                        self.dump_bank_chr(0x0000, 0x2000).await;
+                    }
+                }
+            }
+            13 => {
+                // CPROM has 16 KB of CHR-RAM and no CHR-ROM: its content is whatever the game
+                // last wrote, so this only proves that every page is writable and selectable.
+                // The streamed bytes are the test pattern read back, not a valid ROM dump.
+                for page in 0..4u8 {
+                    self.write_prg_byte(0x8000, page).await;
+                    for address in 0x1000..0x2000u16 {
+                        self.write_chr_byte(address, (address as u8) ^ page.wrapping_mul(0x55)).await;
+                    }
+                    self.dump_bank_chr(0x1000, 0x2000).await;
+                }
+            }
+            69 => {
+                // Commands 0-7 select the eight 1 KB CHR banks
+                let banks = (1u16 << size) * 4;
+                for first in (0..banks).step_by(8) {
+                    for register in 0..8u16 {
+                        self.write_prg_byte(0x8000, register as u8).await;
+                        self.write_prg_byte(0xA000, (first + register) as u8).await;
+                    }
+                    self.dump_bank_chr(0x0000, (banks - first).min(8) * 0x400).await;
+                }
+            }
+            89 => {
+                // CHR is switched as a whole 8 KB bank, numbered by bit 7 (high) and bits 2:0 of
+                // the register; the PRG bits are left at 0
+                let banks = ((1u8 << size) / 2).max(1);
+                for i in 0..banks {
+                    self.write_prg_byte(0x8000, ((i & 0x08) << 4) | (i & 0x07)).await;
+                    self.dump_bank_chr(0x0000, 0x2000).await;
+                }
+            }
+            228 => {
+                // 6 bit CHR bank: the high bits come from A3:A0, the low bits from D1:D0
+                let banks = (self.config.chr / 8).min(64);
                 for i in 0..banks {
-                    self.write_prg_byte(0x8000, 0x02).await;
-                    self.write_prg_byte(0x8001, i as u8).await;
-                    self.dump_bank_chr(0x1000, 0x1400).await;
+                    self.write_prg_byte(0x8000 | (i >> 2), (i & 0x03) as u8).await;
+                    self.dump_bank_chr(0x0000, 0x2000).await;
+                }
+            }
+            184 => {
+                // Bits 2:0 select the 4 KB bank at $0000, bits 6:4 the one at $1000, whose
+                // highest bit is always set: banks 0-3 are read low, banks 4-7 high
+                let banks = (1u8 << size).min(8);
+                for lo in 0..banks.min(4) {
+                    self.write_prg_byte(0x6000, 0x40 | lo).await;
+                    self.dump_bank_chr(0x0000, 0x1000).await;
                 }
+                for hi in 4..banks {
+                    self.write_prg_byte(0x6000, hi << 4).await;
+                    self.dump_bank_chr(0x1000, 0x2000).await;
+                }
+            }
+            21 | 22 | 23 | 25 => {
+                let variant = self.vrc_board_variant();
+                let banks = (1u16 << size) * 4;
+                self.dump_vrc_chr_banks(variant, banks).await;
             }
             _ => {}
         }
@@ -740,13 +2443,17 @@ impl<'d> DumperClass<'d>
         self.set_rd_low();
     }
 
-    async fn dump_snes(&mut self) {
-        self.ciram_ce.set_as_output(Default::default());
+    /// Sets up every pin for the SNES connector, starting from [`Self::reset_to_idle`]: the NES
+    /// only lines become the low address bits and the cartridge is left selected for reading.
+    fn configure_for_snes(&mut self) {
+        self.reset_to_idle();
+        self.current_mode = ConsoleMode::Snes;
+        self.ciram_ce.set_as_output(self.config.bus_drive_strength.into());
         self.ciram_ce.set_low();
-        self.irq.set_as_output(Default::default());
+        self.irq.set_as_output(self.config.bus_drive_strength.into());
         self.irq.set_low();
         for d_index in 0..8 {
-            self.d[d_index].set_as_output(Default::default());
+            self.d[d_index].set_as_output(DATA_PIN_SPEED);
             self.d[d_index].set_low();
         }
 
@@ -756,18 +2463,145 @@ impl<'d> DumperClass<'d>
         self.set_rd_low();
 
         self.set_refresh_low();
+    }
+
+    async fn dump_snes(&mut self) {
+        self.configure_for_snes();
 
+        self.verify_fill = 0;
         let (rom_size, num_banks, rom_type) = self.get_cart_info_snes().await;
-        self.out_channel.send(Msg::DumpSetupData{ rom_size: match rom_type {
+        // The probe writes to the cartridge, so it only runs when asked for
+        if self.config.snes_flash_detect {
+            let (storage, manufacturer) = self.detect_snes_flash(rom_type).await;
+            self.cartridge_info.snes_storage = Some(storage);
+            self.cartridge_info.flash_manufacturer = manufacturer;
+        } else {
+            self.cartridge_info.snes_storage = None;
+            self.cartridge_info.flash_manufacturer = None;
+        }
+        self.control_in_snes();
+        let dump_size = match rom_type {
             v if v == SnesRomType::LO as u8 => {(0x10000 - 0x8000) * num_banks as u32},
             v if v == SnesRomType::HI as u8 => {0x10000 * num_banks as u32},
+            v if v == SnesRomType::SuperFx as u8 => {(0x10000 - 0x8000) * num_banks as u32},
             _ => {0}
-        }}).await;
-        self.read_rom_snes(rom_size, num_banks, rom_type).await;
+        };
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: dump_size }).await;
+        self.running_sum = 0;
+        match self.snes_layout {
+            SnesRomLayout::Standard => self.read_rom_snes(rom_size, num_banks, rom_type).await,
+            SnesRomLayout::Interleaved => self.dump_interleaved_snes(dump_size).await,
+        }
+        // Sent before End: the MTP side stops listening once the transfer is closed. Interleaved
+        // dumps capture nothing to compare, the linear re-read does not follow the chip order, so
+        // no result is sent and diagnostics.txt reports the verify as not run.
+        if self.snes_layout == SnesRomLayout::Standard {
+            let (bytes_checked, mismatch_count) = self.verify_rom_snes(rom_size, rom_type).await;
+            self.out_channel.send(Msg::VerifyResult { bytes_checked, mismatch_count }).await;
+        }
+        // Informational only: some games, fan translations above all, ship with a wrong checksum
+        // and the dump is kept anyway
+        if self.snes_checksum != Some(self.running_sum as u16) {
+            self.out_channel.send(Msg::Warning { code: Msg::WARNING_SNES_CHECKSUM }).await;
+        }
+        self.out_channel.send(Msg::CartridgeInfo { info: self.cartridge_info }).await;
         self.out_channel.send(Msg::End).await;
     }
 
-    async fn get_cart_info_snes(&mut self) -> (u8, u8, u8) {
+    /// Dumps the ROM banks `from_bank` to `to_bank` as the header describes them, bank 0 being
+    /// the first bank of the ROM: $00 for LoROM, $C0 for HiROM. Nothing about the cartridge is
+    /// detected, which makes it a quick check of the first banks before a full dump.
+    async fn dump_snes_partial(&mut self, from_bank: u8, to_bank: u8, rom_type: u8) {
+        self.configure_for_snes();
+        self.data_in();
+        self.control_in_snes();
+        let banks = to_bank.saturating_sub(from_bank) as u32;
+        let dump_size = match rom_type {
+            v if v == SnesRomType::LO as u8 => {(0x10000 - 0x8000) * banks},
+            v if v == SnesRomType::HI as u8 => {0x10000 * banks},
+            _ => {0}
+        };
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: dump_size }).await;
+        self.verify_fill = 0;
+        match rom_type {
+            v if v == SnesRomType::LO as u8 => {self.read_lo_rom_banks(from_bank, to_bank).await;}
+            v if v == SnesRomType::HI as u8 => {
+                self.read_hi_rom_banks(from_bank.saturating_add(192), to_bank.saturating_add(192)).await;
+            }
+            _ => {}
+        }
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Tells a flash repro from a mask ROM cartridge. A complemented byte is written over ROM
+    /// address 0 and put back if it sticks, then the chip is asked for its CFI "QRY" string and
+    /// its autoselect manufacturer code, each followed by a reset command. Mask ROM ignores all
+    /// of it; any write operation on SNES ROM should be refused unless this found flash.
+    /// Only run with `snes_flash_detect` configured.
+    async fn detect_snes_flash(&mut self, rom_type: u8) -> (CartridgeStorage, Option<u8>) {
+        let original = self.read_snes_rom_byte(rom_type, 0x0000).await;
+        self.write_snes_rom_byte(rom_type, 0x0000, !original).await;
+        let written = self.read_snes_rom_byte(rom_type, 0x0000).await;
+        let mut flash = written != original;
+        if flash {
+            self.write_snes_rom_byte(rom_type, 0x0000, original).await;
+        }
+
+        // CFI query, byte mode addresses
+        self.write_snes_rom_byte(rom_type, 0x00AA, 0x98).await;
+        let mut qry = [0u8; 3];
+        for (index, byte) in qry.iter_mut().enumerate() {
+            *byte = self.read_snes_rom_byte(rom_type, 0x0020 + index as u16 * 2).await;
+        }
+        self.write_snes_rom_byte(rom_type, 0x0000, 0xF0).await;
+        flash |= &qry == b"QRY";
+
+        // Autoselect
+        self.write_snes_rom_byte(rom_type, 0x0AAA, 0xAA).await;
+        self.write_snes_rom_byte(rom_type, 0x0555, 0x55).await;
+        self.write_snes_rom_byte(rom_type, 0x0AAA, 0x90).await;
+        let manufacturer = self.read_snes_rom_byte(rom_type, 0x0000).await;
+        self.write_snes_rom_byte(rom_type, 0x0000, 0xF0).await;
+        let manufacturer = (manufacturer != original && self.read_snes_rom_byte(rom_type, 0x0000).await == original)
+            .then_some(manufacturer);
+        flash |= manufacturer.is_some();
+
+        let storage = if flash { CartridgeStorage::Flash } else { CartridgeStorage::MaskRom };
+        (storage, manufacturer)
+    }
+
+    /// SNES bank and address of byte `chip_address` of the ROM chip: LoROM leaves A15 out of the
+    /// chip address and shows it at $8000-$FFFF, HiROM whole in bank $C0.
+    fn snes_rom_address(rom_type: u8, chip_address: u16) -> (u8, u16) {
+        if rom_type == SnesRomType::HI as u8 {
+            (0xC0, chip_address)
+        } else {
+            (0x00, 0x8000 | chip_address)
+        }
+    }
+
+    async fn read_snes_rom_byte(&mut self, rom_type: u8, chip_address: u16) -> u8 {
+        let (bank, address) = Self::snes_rom_address(rom_type, chip_address);
+        self.set_address_b(bank);
+        self.set_address_a(address);
+        self.set_rd_low();
+        Timer::after_nanos(375).await;
+        self.read_snes_data()
+    }
+
+    async fn write_snes_rom_byte(&mut self, rom_type: u8, chip_address: u16, data: u8) {
+        let (bank, address) = Self::snes_rom_address(rom_type, chip_address);
+        self.set_rd_high();
+        self.set_address_b(bank);
+        self.set_address_a(address);
+        self.write_snes_data(data);
+        self.set_wr_low();
+        Timer::after_micros(1).await;
+        self.set_wr_high();
+        self.data_in();
+    }
+
+    async fn get_cart_info_snes(&mut self) -> (u32, u8, u8) {
         self.set_address_b(0b11000000);
         for curr_byte in 0..1024 {
             self.set_address_a(curr_byte);
@@ -776,19 +2610,33 @@ impl<'d> DumperClass<'d>
         self.check_cart_snes().await
     }
 
-    async fn check_cart_snes(&mut self) -> (u8, u8, u8) {
+    async fn check_cart_snes(&mut self) -> (u32, u8, u8) {
         self.data_in();
 
+        // Offsets below are relative to the usual $FFB0 header, whichever location it came from
         let header_start = 0xFFB0;
         let mut snes_header = [0u8;80];
-        self.set_address_b(0x00);
-        for c in 0..80 {
-            let curr_byte = header_start + c as u16;
-            self.set_address_a(curr_byte);
-            Timer::after_nanos(75000).await;
-
-            snes_header[c] = self.read_snes_data();
+        let mut best_score = None;
+        for (bank, offset) in SNES_HEADER_LOCATIONS {
+            let header = self.read_header_at_extended(bank, offset).await;
+            let score = Self::score_snes_header(&header);
+            // Ties keep the earlier, more common location
+            if best_score.map_or(true, |best| score > best) {
+                best_score = Some(score);
+                snes_header = header;
+            }
         }
+        let interleaved_header = self.read_interleaved_header().await;
+        self.snes_layout = if Self::score_snes_header(&interleaved_header) > best_score.unwrap_or(0) {
+            snes_header = interleaved_header;
+            SnesRomLayout::Interleaved
+        } else {
+            SnesRomLayout::Standard
+        };
+        let complement = u16::from_le_bytes([snes_header[(0xFFDC - header_start) as usize], snes_header[(0xFFDD - header_start) as usize]]);
+        let checksum = u16::from_le_bytes([snes_header[(0xFFDE - header_start) as usize], snes_header[(0xFFDF - header_start) as usize]]);
+        self.snes_checksum = if checksum ^ complement == 0xFFFF { Some(checksum) } else { None };
+
         let mut rom_type = match snes_header[(0xFFD5 - header_start) as usize] {
             v if ((v >> 5) != 1) => {SnesRomType::LO as u8},
             0x35 => {SnesRomType::EX as u8},
@@ -797,7 +2645,18 @@ impl<'d> DumperClass<'d>
         };
 
         let rom_chips = snes_header[(0xFFD6 - header_start) as usize];
-        let mut rom_size = 1;
+        // ROM + GSU, with or without RAM and battery
+        if matches!(rom_chips, 0x13..=0x15 | 0x1A) {
+            rom_type = SnesRomType::SuperFx as u8;
+        }
+        let known_types = [
+            SnesRomType::LO as u8, SnesRomType::HI as u8, SnesRomType::SA as u8,
+            SnesRomType::EX as u8, SnesRomType::SuperFx as u8,
+        ];
+        if !known_types.contains(&rom_type) {
+            return (0, 0, rom_type);
+        }
+        let mut rom_size: u32 = 1;
         let mut num_banks = 0;
         if rom_chips == 69 {
             rom_size = 48;
@@ -823,21 +2682,112 @@ impl<'d> DumperClass<'d>
             rom_size = 40;
             num_banks = 80;
         } else {
-            let rom_size_exp = snes_header[(0xFFD7 - header_start) as usize] - 7;
+            // Capped at 2^10 Mbit: anything larger is a corrupted header, not a real cartridge
+            let rom_size_exp = snes_header[(0xFFD7 - header_start) as usize].saturating_sub(7).min(10);
             for _ in 0..rom_size_exp {
                 rom_size *= 2;
             }
-            if rom_type == SnesRomType::EX as u8 || rom_type == SnesRomType::SA as u8 {
-                num_banks = rom_size as u8 * 2
-            } else {
-                num_banks = ((rom_size as usize * 1024 * (1024 / 8)) / (0x8000 + (rom_type as usize * 0x8000))) as u8;
+            if rom_type == SnesRomType::EX as u8 || rom_type == SnesRomType::SA as u8 {
+                num_banks = (rom_size * 2).min(u8::MAX as u32) as u8
+            } else {
+                let bank_size: u32 = match rom_type {
+                    v if v == SnesRomType::LO as u8 => 0x8000,
+                    v if v == SnesRomType::HI as u8 => 0x10000,
+                    v if v == SnesRomType::SuperFx as u8 => 0x8000,
+                    _ => 0,
+                };
+                num_banks = match (rom_size * 1024 * (1024 / 8)).checked_div(bank_size) {
+                    Some(banks) => banks.min(u8::MAX as u32) as u8,
+                    None => 0,
+                };
+            }
+        }
+
+        if rom_type == SnesRomType::SuperFx as u8 {
+            // The GSU address space caps the ROM at 2 MB, further banks would only be mirrors
+            num_banks = num_banks.min(0x40);
+        }
+
+        (rom_size, num_banks, rom_type)
+    }
+
+    /// Reads the 80 byte header block at `bank:offset`.
+    async fn read_header_at_extended(&mut self, bank: u8, offset: u16) -> [u8; 80] {
+        let mut header = [0u8; 80];
+        self.set_address_b(bank);
+        for c in 0..header.len() {
+            self.set_address_a(offset + c as u16);
+            Timer::after_nanos(75000).await;
+
+            header[c] = self.read_snes_data();
+        }
+        header
+    }
+
+    /// Reads byte `chip_offset` of one chip of an interleaved cartridge, `chip` indexing
+    /// [`SNES_INTERLEAVED_CHIP_BANKS`].
+    async fn read_interleaved_snes_byte(&mut self, chip: usize, chip_offset: u32) -> u8 {
+        self.set_address_b(SNES_INTERLEAVED_CHIP_BANKS[chip] + (chip_offset >> 16) as u8);
+        self.set_address_a(chip_offset as u16);
+        Timer::after_nanos(375).await;
+        self.read_snes_data()
+    }
+
+    /// Reads the 80 byte block at $FFB0 of the de-interleaved ROM.
+    async fn read_interleaved_header(&mut self) -> [u8; 80] {
+        let mut header = [0u8; 80];
+        for c in 0..header.len() {
+            let offset = 0xFFB0 + c as u32;
+            header[c] = self.read_interleaved_snes_byte((offset & 1) as usize, offset >> 1).await;
+        }
+        header
+    }
+
+    /// Dumps `rom_bytes` of an interleaved cartridge: a chunk of each chip is read, then the two
+    /// are woven back together, even bytes from the first chip and odd bytes from the second.
+    async fn dump_interleaved_snes(&mut self, rom_bytes: u32) {
+        self.data_in();
+        self.control_in_snes();
+        let mut chunk = [0u8; 2 * Msg::DATA_CHANNEL_SIZE];
+        for chip_offset in (0..rom_bytes / 2).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for c in 0..chunk.len() {
+                let chip = c / Msg::DATA_CHANNEL_SIZE;
+                let offset = chip_offset + (c % Msg::DATA_CHANNEL_SIZE) as u32;
+                chunk[c] = self.read_interleaved_snes_byte(chip, offset).await;
+            }
+            for half in 0..2 {
+                for c in 0..Msg::DATA_CHANNEL_SIZE {
+                    let index = half * Msg::DATA_CHANNEL_SIZE + c;
+                    self.buffer[c] = chunk[(index % 2) * Msg::DATA_CHANNEL_SIZE + index / 2];
+                    self.running_sum += self.buffer[c] as u32;
+                }
+                self.send_buffer(Msg::DATA_CHANNEL_SIZE).await;
             }
         }
+    }
 
-        (rom_size, num_banks, rom_type)
+    /// Rates how much an 80 byte block looks like a SNES header: matching checksum and
+    /// complement, a known map mode, a plausible ROM size and a printable title.
+    fn score_snes_header(header: &[u8; 80]) -> u8 {
+        let mut score = 0;
+        let complement = u16::from_le_bytes([header[0x2C], header[0x2D]]);
+        let checksum = u16::from_le_bytes([header[0x2E], header[0x2F]]);
+        if checksum ^ complement == 0xFFFF {
+            score += 4;
+        }
+        if header[0x25] & 0xE0 == 0x20 {
+            score += 2;
+        }
+        if (0x08..=0x0D).contains(&header[0x27]) {
+            score += 1;
+        }
+        if header[0x10..0x25].iter().all(|c| (0x20..0x7F).contains(c)) {
+            score += 1;
+        }
+        score
     }
 
-    async fn read_rom_snes(&mut self, rom_size: u8,  num_banks: u8, rom_type: u8) {
+    async fn read_rom_snes(&mut self, rom_size: u32,  num_banks: u8, rom_type: u8) {
         self.data_in();
         self.control_in_snes();
         match rom_type {
@@ -850,12 +2800,44 @@ impl<'d> DumperClass<'d>
                 }
             }
             v if v == SnesRomType::HI as u8 =>  {self.read_hi_rom_banks(192, num_banks + 192).await;}
+            v if v == SnesRomType::SuperFx as u8 =>  {self.read_superfx_rom_banks(num_banks).await;}
             _ => {}
         }
     }
 
+    fn capture_verify_data(&mut self, length: usize) {
+        let length = length.min(self.verify_buf.len() - self.verify_fill);
+        self.verify_buf[self.verify_fill..self.verify_fill + length].copy_from_slice(&self.buffer[..length]);
+        self.verify_fill += length;
+    }
+
+    /// Re-reads the start of the ROM and compares it against the bytes captured while streaming.
+    async fn verify_rom_snes(&mut self, rom_size: u32, rom_type: u8) -> (u32, u32) {
+        let (bank, start) = match rom_type {
+            v if v == SnesRomType::LO as u8 => {(if rom_size > 24 {0x80} else {0}, 0x8000u16)}
+            v if v == SnesRomType::HI as u8 => {(192, 0x0000u16)}
+            v if v == SnesRomType::SuperFx as u8 => {(0, 0x8000u16)}
+            _ => {return (0, 0)}
+        };
+        self.data_in();
+        self.control_in_snes();
+        self.set_address_b(bank);
+        let mut mismatch_count = 0;
+        for c in 0..self.verify_fill {
+            self.set_address_a(start + c as u16);
+            Timer::after_nanos(375).await;
+            if self.read_snes_data() != self.verify_buf[c] {
+                mismatch_count += 1;
+            }
+        }
+        (self.verify_fill as u32, mismatch_count)
+    }
+
     async fn read_lo_rom_banks(&mut self, start: u8, end: u8) {
         for curr_bank in start..end {
+            if curr_bank != start {
+                Timer::after_millis(self.config.inter_bank_delay_ms as u64).await;
+            }
             self.set_address_b(curr_bank);
             let range = 0x8000..=0xFFFF;
             for chunk_start in range.step_by(Msg::DATA_CHANNEL_SIZE) {
@@ -863,16 +2845,26 @@ impl<'d> DumperClass<'d>
                 let bytes_len = bytes_range.len();
                 for (c, curr_byte) in bytes_range.enumerate() {
                     self.set_address_a(curr_byte);
-                    Timer::after_nanos(375).await;
+                    Timer::after_nanos(self.config.intra_bank_delay_ns as u64).await;
                     self.buffer[c] = self.read_snes_data();
+                    self.running_sum += self.buffer[c] as u32;
                 }
-                self.out_channel.send(Msg::Data{data: *self.buffer, length: bytes_len}).await;
+                self.capture_verify_data(bytes_len);
+                self.send_buffer(bytes_len).await;
             }
         }
     }
 
+    /// Super FX boards show the ROM to the SNES in LoROM banks $00-$3F, $8000-$FFFF.
+    async fn read_superfx_rom_banks(&mut self, num_banks: u8) {
+        self.read_lo_rom_banks(0x00, num_banks).await;
+    }
+
     async fn read_hi_rom_banks(&mut self, start: u8, end: u8) {
         for curr_bank in start..end {
+            if curr_bank != start {
+                Timer::after_millis(self.config.inter_bank_delay_ms as u64).await;
+            }
             self.set_address_b(curr_bank);
             let range = 0..=0xFFFF;
             for chunk_start in range.step_by(Msg::DATA_CHANNEL_SIZE) {
@@ -880,10 +2872,12 @@ impl<'d> DumperClass<'d>
                 let bytes_len = bytes_range.len();
                 for (c, curr_byte) in bytes_range.enumerate() {
                     self.set_address_a(curr_byte);
-                    Timer::after_nanos(375).await;
+                    Timer::after_nanos(self.config.intra_bank_delay_ns as u64).await;
                     self.buffer[c] = self.read_snes_data();
+                    self.running_sum += self.buffer[c] as u32;
                 }
-                self.out_channel.send(Msg::Data{data: *self.buffer, length: bytes_len}).await;
+                self.capture_verify_data(bytes_len);
+                self.send_buffer(bytes_len).await;
             }
         }
     }
@@ -930,9 +2924,9 @@ impl<'d> DumperClass<'d>
 
     async fn write_byte_sms(&mut self, my_address: u16, my_data: u8) {
         for i in 0..7 {
-            self.d_snes[i].set_as_output(Default::default());
+            self.d_snes[i].set_as_output(DATA_PIN_SPEED);
         }
-        self.ciram_a10.set_as_output(Default::default());
+        self.ciram_a10.set_as_output(DATA_PIN_SPEED);
         self.set_address_sms(my_address);
         self.cs.set_level(Level::from((my_address & (1 << 15)) > 0));
         self.set_data_sms(my_data);
@@ -1023,10 +3017,11 @@ impl<'d> DumperClass<'d>
     }
 
     async fn setup_sms(&mut self) -> u32 {
-        self.ciram_ce.set_as_output(Default::default());
-        self.irq.set_as_output(Default::default());
+        self.current_mode = ConsoleMode::Sms;
+        self.ciram_ce.set_as_output(self.config.bus_drive_strength.into());
+        self.irq.set_as_output(self.config.bus_drive_strength.into());
         for i in 0..7 {
-            self.d[i].set_as_output(Default::default());
+            self.d[i].set_as_output(DATA_PIN_SPEED);
         }
         self.reset.set_high();
         self.wr.set_high();
@@ -1053,9 +3048,713 @@ impl<'d> DumperClass<'d>
                 for curr_byte in 0..self.buffer.len() as u16 {
                     self.buffer[curr_byte as usize] = self.read_byte_sms((if cart_size == 32768 { 0 } else { 0x8000 }) + curr_buffer + curr_byte).await;
                 }
-                self.out_channel.send(Msg::Data{data: *self.buffer, length: self.buffer.len()}).await;
+                self.send_buffer(self.buffer.len()).await;
             }
             Timer::after_nanos(63).await;
         }
     }
-}
\ No newline at end of file
+
+    /// The Game Boy cartridge sits on the SNES side of the connector: A0-A15 on bus A, D0-D7 on
+    /// the SNES data lines, /RD, /WR and /CS (SRAM select) on the SNES control lines.
+    fn setup_gb(&mut self) {
+        self.current_mode = ConsoleMode::Gb;
+        self.ciram_ce.set_as_output(self.config.bus_drive_strength.into());
+        self.irq.set_as_output(self.config.bus_drive_strength.into());
+        for d_index in 0..8 {
+            self.d[d_index].set_as_output(DATA_PIN_SPEED);
+            self.d[d_index].set_low();
+        }
+        self.data_in();
+        self.set_reset_high();
+        self.set_wr_high();
+        self.set_rd_high();
+        self.set_cs_high();
+    }
+
+    fn write_snes_data(&mut self, data: u8) {
+        for (index, pin) in self.d_snes.iter_mut().enumerate() {
+            let true_index = if index < 2 {index} else {index+1} ;
+            pin.set_level(Level::from((data & (1 << true_index)) > 0));
+            pin.set_as_output(DATA_PIN_SPEED);
+        }
+        self.ciram_a10.set_level(Level::from((data & (1 << 2)) > 0));
+        self.ciram_a10.set_as_output(DATA_PIN_SPEED);
+    }
+
+    async fn read_gb_byte(&mut self, address: u16) -> u8 {
+        self.set_address_a(address);
+        self.set_rd_low();
+        Timer::after_nanos(375).await;
+        let data = self.read_snes_data();
+        self.set_rd_high();
+        data
+    }
+
+    async fn write_gb_byte(&mut self, address: u16, data: u8) {
+        self.set_rd_high();
+        self.set_address_a(address);
+        self.write_snes_data(data);
+        self.set_wr_low();
+        Timer::after_micros(1).await;
+        self.set_wr_high();
+        self.data_in();
+    }
+
+    async fn dump_gb_range(&mut self, from: u16, to: u16) {
+        for address in (from..to).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in 0..self.buffer.len() {
+                self.buffer[x] = self.read_gb_byte(address + x as u16).await;
+                self.gb_sum = self.gb_sum.wrapping_add(self.buffer[x] as u16);
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+    }
+
+    /// Reads the cartridge type, the number of 16 KB ROM banks and the number of 8 KB RAM banks
+    /// from the header.
+    async fn get_cart_info_gb(&mut self) -> (u8, u16, u8) {
+        let cart_type = self.read_gb_byte(0x0147).await;
+        let rom_banks = 2u16 << self.read_gb_byte(0x0148).await.min(8);
+        let ram_banks = match self.read_gb_byte(0x0149).await {
+            0x02 => 1,
+            0x03 => 4,
+            0x04 => 16,
+            0x05 => 8,
+            _ => 0,
+        };
+        (cart_type, rom_banks, ram_banks)
+    }
+
+    async fn dump_gb(&mut self) {
+        self.setup_gb();
+        let mut header = [0u8; 0x50];
+        for (x, byte) in header.iter_mut().enumerate() {
+            *byte = self.read_gb_byte(0x0100 + x as u16).await;
+        }
+        let header_checksum = header[0x34..=0x4C].iter()
+            .fold(0u8, |checksum, byte| checksum.wrapping_sub(*byte).wrapping_sub(1));
+        if header_checksum != header[0x4D] {
+            self.out_channel.send(Msg::Error { code: Msg::ERROR_GB_HEADER_CHECKSUM, context: header[0x4D] as u32 }).await;
+        }
+
+        let (cart_type, rom_banks, _) = self.get_cart_info_gb().await;
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: rom_banks as u32 * 0x4000 }).await;
+        self.gb_sum = 0;
+        match cart_type {
+            // ROM only, ROM+RAM and ROM+RAM+BATTERY
+            0x00 | 0x08 | 0x09 => {
+                self.dump_gb_range(0x0000, 0x8000).await;
+            }
+            0x0F..=0x13 => {
+                self.dump_gb_range(0x0000, 0x4000).await;
+                self.dump_mbc3_rom(rom_banks).await;
+            }
+            0x19..=0x1E => {
+                self.dump_gb_range(0x0000, 0x4000).await;
+                self.dump_mbc5_rom(rom_banks).await;
+            }
+            _ => {
+                // MBC1 (0x01-0x03), MBC2 (0x05-0x06) and the other controllers taking the bank
+                // number at $2000-$3FFF, like HuC1
+                self.dump_gb_range(0x0000, 0x4000).await;
+                self.dump_mbc1_rom(rom_banks).await;
+            }
+        }
+        // The global checksum covers every ROM byte but its own two
+        let global_checksum = u16::from_be_bytes([header[0x4E], header[0x4F]]);
+        let sum = self.gb_sum.wrapping_sub(header[0x4E] as u16).wrapping_sub(header[0x4F] as u16);
+        if sum != global_checksum {
+            self.out_channel.send(Msg::Warning { code: Msg::WARNING_GB_GLOBAL_CHECKSUM }).await;
+        }
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Dumps ROM banks 1 to `banks - 1` of an MBC1 cart. The low 5 bits of the bank number go to
+    /// $2100, the upper 2 bits to $4000. The MBC1 turns a 0 in the low bits into 1, so banks
+    /// $20, $40 and $60 never show at $4000: they are read at $0000 in banking mode 1 ($6000 = 1),
+    /// where the upper bits also apply to the lower window. $2100 keeps A8 set, which the MBC2
+    /// needs to take the write as a bank number.
+    async fn dump_mbc1_rom(&mut self, banks: u16) {
+        for bank in 1..banks.min(128) {
+            self.write_gb_byte(0x4000, ((bank >> 5) & 0x03) as u8).await;
+            if bank & 0x1F == 0 {
+                self.write_gb_byte(0x6000, 0x01).await;
+                self.dump_gb_range(0x0000, 0x4000).await;
+                self.write_gb_byte(0x6000, 0x00).await;
+            } else {
+                self.write_gb_byte(0x2100, (bank & 0x1F) as u8).await;
+                self.dump_gb_range(0x4000, 0x8000).await;
+            }
+        }
+        self.write_gb_byte(0x4000, 0x00).await;
+    }
+
+    /// Dumps ROM banks 1 to `banks - 1` of an MBC3 cart, the 7 bit bank number going to $2000.
+    async fn dump_mbc3_rom(&mut self, banks: u16) {
+        for bank in 1..banks.min(128) {
+            self.write_gb_byte(0x2000, (bank & 0x7F) as u8).await;
+            self.dump_gb_range(0x4000, 0x8000).await;
+        }
+    }
+
+    /// Dumps ROM banks 1 to `banks - 1` through the switchable window at $4000-$7FFF. The 9 bit
+    /// bank number is split between $2000 (low 8 bits) and $3000 (bit 8).
+    async fn dump_mbc5_rom(&mut self, banks: u16) {
+        for bank in 1..banks.min(512) {
+            self.write_gb_byte(0x2000, (bank & 0xFF) as u8).await;
+            self.write_gb_byte(0x3000, ((bank >> 8) & 0x01) as u8).await;
+            self.dump_gb_range(0x4000, 0x8000).await;
+        }
+    }
+
+    /// Dumps up to 16 RAM banks of 8 KB through the window at $A000-$BFFF.
+    async fn dump_mbc5_ram(&mut self, banks: u8) {
+        self.write_gb_byte(0x0000, 0x0A).await;  // RAM enable
+        self.set_cs_low();
+        for bank in 0..banks.min(16) {
+            self.write_gb_byte(0x4000, bank).await;
+            self.dump_gb_range(0xA000, 0xC000).await;
+        }
+        self.set_cs_high();
+        self.write_gb_byte(0x0000, 0x00).await;  // RAM disable
+    }
+
+    async fn dump_gb_sram(&mut self) {
+        self.setup_gb();
+        let (cart_type, _, ram_banks) = self.get_cart_info_gb().await;
+        // MBC3+TIMER and MBC3+TIMER+RAM+BATTERY
+        let has_rtc = matches!(cart_type, 0x0F | 0x10);
+        let rtc_size = if has_rtc { GB_RTC_SNAPSHOT_SIZE as u32 } else { 0 };
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: ram_banks as u32 * 0x2000 + rtc_size }).await;
+        self.dump_mbc5_ram(ram_banks).await;
+        if has_rtc {
+            self.dump_mbc3_rtc().await;
+        }
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Appends the MBC3 clock to the save in the 48 byte layout emulators read after the RAM:
+    /// seconds, minutes, hours, day low and day high as 32 bit words, the same five latched values,
+    /// then a 64 bit UNIX timestamp. The dumper has no wall clock, so the timestamp is left at 0.
+    async fn dump_mbc3_rtc(&mut self) {
+        let mut snapshot = [0u8; GB_RTC_SNAPSHOT_SIZE];
+        self.write_gb_byte(0x0000, 0x0A).await;  // RAM and RTC enable
+        self.write_gb_byte(0x6000, 0x00).await;  // Latch clock data
+        self.write_gb_byte(0x6000, 0x01).await;
+        for register in 0..5u8 {
+            self.write_gb_byte(0x4000, 0x08 + register).await;
+            self.set_cs_low();
+            let value = self.read_gb_byte(0xA000).await;
+            self.set_cs_high();
+            let index = register as usize * 4;
+            snapshot[index] = value;
+            snapshot[20 + index] = value;
+        }
+        self.write_gb_byte(0x0000, 0x00).await;
+
+        for chunk in snapshot.chunks(Msg::DATA_CHANNEL_SIZE) {
+            self.buffer[..chunk.len()].copy_from_slice(chunk);
+            self.send_buffer(chunk.len()).await;
+        }
+    }
+
+    /// HuCards need 21 address lines: A0-A15 come from the NES address bus, A16-A20 from
+    /// `d_snes[0..5]`, while `d_snes[5]` and `d_snes[6]` drive /CE and /OE. Data is read on the NES
+    /// data lines.
+    fn setup_pce(&mut self) {
+        self.current_mode = ConsoleMode::Pce;
+        for d_index in 0..8 {
+            self.d[d_index].set_as_input(Pull::Up);
+        }
+        for pin in self.d_snes.iter_mut() {
+            pin.set_high();
+            pin.set_as_output(DATA_PIN_SPEED);
+        }
+    }
+
+    /// Gives the SNES data lines back as inputs.
+    fn release_pce(&mut self) {
+        self.data_in();
+    }
+
+    fn set_pce_address(&mut self, address: u32) {
+        for index in 0..16 {
+            self.a[index].set_level(Level::from((address & (1 << index)) > 0));
+        }
+        for index in 0..5 {
+            self.d_snes[index].set_level(Level::from((address & (1 << (16 + index))) > 0));
+        }
+    }
+
+    async fn read_pce_byte(&mut self, address: u32) -> u8 {
+        self.set_pce_address(address);
+        self.d_snes[5].set_low();  // /CE
+        self.d_snes[6].set_low();  // /OE
+        Timer::after_nanos(375).await;
+        let data = self.read_data();
+        self.d_snes[6].set_high();
+        self.d_snes[5].set_high();
+        data
+    }
+
+    async fn dump_pce(&mut self) {
+        self.setup_pce();
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: PCE_ROM_SIZE }).await;
+        for address in (0..PCE_ROM_SIZE).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in 0..self.buffer.len() {
+                self.buffer[x] = self.read_pce_byte(address + x as u32).await;
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.release_pce();
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// The N64 cartridge is read as a plain 16 bit ROM: word address bits 0-15 on the NES
+    /// address lines, bits 16-23 on the NES control lines the SNES bus uses as A0-A7, D0-D7 on
+    /// the NES data lines and D8-D15 on the SNES ones, /CE on `cs` and /OE on `rd`.
+    fn setup_n64(&mut self) {
+        self.reset_to_idle();
+        self.current_mode = ConsoleMode::N64;
+        self.ciram_ce.set_as_output(self.config.bus_drive_strength.into());
+        self.ciram_ce.set_low();
+        self.irq.set_as_output(self.config.bus_drive_strength.into());
+        self.irq.set_low();
+    }
+
+    /// Drives a word address on the 16 bit ROM wiring described in [`Self::setup_n64`], shared by
+    /// the N64 and Game Boy Advance adapters.
+    fn set_rom16_address(&mut self, word_address: u32) {
+        for index in 0..16 {
+            self.a[index].set_level(Level::from((word_address & (1 << index)) > 0));
+        }
+        let upper = (word_address >> 16) as u8;
+        self.m2.set_level(Level::from((upper & (1 << 0)) > 0));
+        self.pgr_ce.set_level(Level::from((upper & (1 << 1)) > 0));
+        self.chr_wr.set_level(Level::from((upper & (1 << 2)) > 0));
+        self.ciram_ce.set_level(Level::from((upper & (1 << 3)) > 0));
+        self.a15.set_level(Level::from((upper & (1 << 4)) > 0));
+        self.chr_rd.set_level(Level::from((upper & (1 << 5)) > 0));
+        self.irq.set_level(Level::from((upper & (1 << 6)) > 0));
+        self.prg_rw.set_level(Level::from((upper & (1 << 7)) > 0));
+    }
+
+    /// Reads the big endian word at byte address `addr`.
+    async fn read_n64_word(&mut self, addr: u32) -> u16 {
+        self.read_rom16_word(addr >> 1).await
+    }
+
+    /// Finds the ROM size from the address lines the cartridge ignores: past its end the ROM
+    /// mirrors, so the first power of two size whose start reads as the start of the ROM is it.
+    async fn detect_n64_rom_size(&mut self) -> u32 {
+        let mut size = N64_MIN_ROM_SIZE;
+        while size < N64_MAX_ROM_SIZE {
+            let mut mirrored = true;
+            for word in 0..N64_MIRROR_CHECK_WORDS {
+                if self.read_n64_word(word * 2).await != self.read_n64_word(size + word * 2).await {
+                    mirrored = false;
+                    break;
+                }
+            }
+            if mirrored {
+                break;
+            }
+            size *= 2;
+        }
+        size
+    }
+
+    /// Dumps an N64 cartridge as a `.z64` image, the big endian byte order of the cartridge bus.
+    async fn dump_n64(&mut self) {
+        self.setup_n64();
+        let rom_size = self.detect_n64_rom_size().await;
+        self.out_channel.send(Msg::DumpSetupData{ rom_size }).await;
+        for address in (0..rom_size).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in (0..self.buffer.len()).step_by(2) {
+                let word = self.read_n64_word(address + x as u32).await;
+                self.buffer[x..x + 2].copy_from_slice(&word.to_be_bytes());
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.reset_to_idle();
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// The Genesis adapter uses the 16 bit wiring of [`Self::setup_n64`]. The 68000 bus has no A0,
+    /// so cartridge A1-A23 are the word address lines and /CE, /OE are `cs` and `rd`.
+    fn setup_genesis(&mut self) {
+        self.setup_n64();
+        self.current_mode = ConsoleMode::Genesis;
+    }
+
+    /// ROM size from the end address in the header, rounded up to a whole word and capped at
+    /// [`GENESIS_MAX_ROM_SIZE`]. An erased or garbled header gives the cap.
+    async fn detect_genesis_rom_size(&mut self) -> u32 {
+        let high = self.read_rom16_word(GENESIS_ROM_END_ADDRESS >> 1).await as u32;
+        let low = self.read_rom16_word((GENESIS_ROM_END_ADDRESS >> 1) + 1).await as u32;
+        let end = (high << 16) | low;
+        if end < GENESIS_ROM_END_ADDRESS || end >= GENESIS_MAX_ROM_SIZE {
+            GENESIS_MAX_ROM_SIZE
+        } else {
+            (end + 2) & !1
+        }
+    }
+
+    /// Dumps a Genesis / Mega Drive cartridge as a raw `.md` binary, each word big endian as the
+    /// 68000 stores it.
+    async fn dump_genesis(&mut self) {
+        self.setup_genesis();
+        let rom_size = self.detect_genesis_rom_size().await;
+        self.out_channel.send(Msg::DumpSetupData{ rom_size }).await;
+        for address in (0..rom_size).step_by(Msg::DATA_CHANNEL_SIZE) {
+            for x in (0..self.buffer.len()).step_by(2) {
+                let word = self.read_rom16_word((address + x as u32) >> 1).await;
+                self.buffer[x..x + 2].copy_from_slice(&word.to_be_bytes());
+            }
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.reset_to_idle();
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Reads an Atari 2600 address: A0-A12 on the NES address lines, A12 selecting the
+    /// cartridge, data on the NES data lines and M2 standing in for PHI2.
+    async fn read_atari2600_byte(&mut self, address: u16) -> u8 {
+        self.m2.set_low();
+        for index in 0..13 {
+            self.a[index].set_level(Level::from((address & (1 << index)) > 0));
+        }
+        self.m2.set_high();
+        Timer::after_nanos(500).await;
+        let data = self.read_data();
+        self.m2.set_low();
+        data
+    }
+
+    /// Dumps an Atari 2600 cartridge as a raw `.a26` binary. The cartridge shows 4 KB at
+    /// $1000-$1FFF, bank switched by reading hotspots: $1FF8+ (F8), $1FF6+ (F6) or $1FF4+ (F4)
+    /// for 4 KB banks, $1FE0-$1FE6 for the 2 KB slices 0-6 of E7 at $1000. $1FE7 maps the E7
+    /// RAM rather than slice 7, which is read from $1800-$1FFF where it is always mapped.
+    /// Reading a hotspot while dumping switches the bank, so the bank is selected again before
+    /// every byte.
+    async fn dump_atari2600(&mut self) {
+        self.configure_for_nes();
+        // (first hotspot, banks, bank size)
+        let (hotspot, banks, bank_size): (u16, u16, u16) = match self.config.atari2600_mapper {
+            1 => (0x1FF8, 2, 0x1000),
+            2 => (0x1FF6, 4, 0x1000),
+            3 => (0x1FF4, 8, 0x1000),
+            4 => (0x1FE0, 8, 0x0800),
+            _ => (0x0000, 1, 0x1000),
+        };
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: banks as u32 * bank_size as u32 }).await;
+        for bank in 0..banks {
+            let fixed_slice = self.config.atari2600_mapper == 4 && bank == banks - 1;
+            let base = if fixed_slice { 0x1800 } else { 0x1000 };
+            for address in (base..base + bank_size).step_by(Msg::DATA_CHANNEL_SIZE) {
+                for x in 0..self.buffer.len() {
+                    if banks > 1 && !fixed_slice {
+                        self.read_atari2600_byte(hotspot + bank).await;
+                    }
+                    self.buffer[x] = self.read_atari2600_byte(address + x as u16).await;
+                }
+                self.send_buffer(self.buffer.len()).await;
+            }
+        }
+        self.reset_to_idle();
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// The Game Boy Advance adapter uses the N64 wiring of [`Self::setup_n64`], the EEPROM serial
+    /// line DQ being D0 and its clock `wr`.
+    fn setup_gba(&mut self) {
+        self.setup_n64();
+        self.current_mode = ConsoleMode::Gba;
+    }
+
+    /// Looks for the save library ID strings in the first [`GBA_SAVE_SCAN_SIZE`] bytes of the ROM.
+    /// The EEPROM library string does not tell the chip size, [`Self::is_gba_eeprom_512b`] does.
+    async fn detect_gba_save_type(&mut self) -> GbaSaveType {
+        const IDS: [(&[u8], GbaSaveType); 3] = [
+            (b"EEPROM_V", GbaSaveType::Eeprom8k),
+            (b"SRAM_V", GbaSaveType::Sram),
+            (b"FLASH", GbaSaveType::Flash),
+        ];
+        // The library strings are word aligned, the window holds the last 8 bytes read
+        let mut window = [0u8; 8];
+        for addr in (0..GBA_SAVE_SCAN_SIZE).step_by(2) {
+            // Nothing is sent while scanning, the watchdog would fire before the end of the ROM
+            if addr % GBA_SAVE_SCAN_KICK_INTERVAL == 0 {
+                crate::kick_watchdog();
+            }
+            let word = self.read_rom16_word(addr >> 1).await;
+            window.copy_within(2.., 0);
+            window[6..].copy_from_slice(&word.to_le_bytes());
+            for (id, save_type) in IDS {
+                if window.starts_with(id) {
+                    return match save_type {
+                        GbaSaveType::Eeprom8k if self.is_gba_eeprom_512b().await => GbaSaveType::Eeprom512,
+                        _ => save_type,
+                    };
+                }
+            }
+        }
+        GbaSaveType::None
+    }
+
+    /// Sends a read request with a 6 bit address. A 512 B EEPROM takes it as complete and
+    /// answers with its 4 dummy 0 bits; an 8 KB one is still taking address bits, leaves DQ to
+    /// its pull-up and the same 4 bits read as 1. The save content plays no part, so blank saves
+    /// are sized like any other.
+    async fn is_gba_eeprom_512b(&mut self) -> bool {
+        self.set_rom16_address(GBA_EEPROM_WORD_ADDRESS);
+        self.set_cs_low();
+        self.eeprom_clock_out(0b11 << 7, 9).await;  // Read request, block 0, stop bit
+        let dummy = self.eeprom_clock_in(4).await;
+        if dummy == 0 {
+            // Let the 512 B chip finish sending the block
+            self.eeprom_clock_in(64).await;
+        }
+        self.set_cs_high();
+        dummy == 0
+    }
+
+    /// Reads the word at word address `word_address`, D8-D15 in the upper byte. The two halves of
+    /// the data bus are sampled one after the other during the same /OE pulse.
+    async fn read_rom16_word(&mut self, word_address: u32) -> u16 {
+        self.set_rom16_address(word_address);
+        self.set_cs_low();
+        self.set_rd_low();
+        Timer::after_nanos(375).await;
+        let low = self.read_data();
+        let high = self.read_snes_data();
+        self.set_rd_high();
+        self.set_cs_high();
+        u16::from_be_bytes([high, low])
+    }
+
+    /// Shifts the low `count` bits of `bits` out on DQ, most significant first.
+    async fn eeprom_clock_out(&mut self, bits: u64, count: usize) {
+        self.d[0].set_as_output(DATA_PIN_SPEED);
+        for index in (0..count).rev() {
+            self.d[0].set_level(Level::from((bits >> index) & 1 != 0));
+            self.set_wr_low();
+            Timer::after_nanos(250).await;
+            self.set_wr_high();
+            Timer::after_nanos(250).await;
+        }
+        self.d[0].set_as_input(Pull::Up);
+    }
+
+    /// Shifts `count` bits in from DQ, the first one ending up the most significant.
+    async fn eeprom_clock_in(&mut self, count: usize) -> u64 {
+        let mut bits = 0u64;
+        for _ in 0..count {
+            self.set_wr_low();
+            Timer::after_nanos(250).await;
+            bits = (bits << 1) | self.d[0].is_high() as u64;
+            self.set_wr_high();
+            Timer::after_nanos(250).await;
+        }
+        bits
+    }
+
+    /// Reads the 8 byte EEPROM block `block`, addressed with `address_bits` bits: a read request
+    /// (`11`, the address, a stop bit), then 4 dummy bits before the 64 data bits.
+    async fn read_gba_eeprom_block(&mut self, block: u16, address_bits: usize) -> u64 {
+        self.set_rom16_address(GBA_EEPROM_WORD_ADDRESS);
+        self.set_cs_low();
+        let request = (0b11 << (address_bits + 1)) | ((block as u64) << 1);
+        self.eeprom_clock_out(request, address_bits + 3).await;
+        let data = self.eeprom_clock_in(68).await;
+        self.set_cs_high();
+        data
+    }
+
+    async fn read_gba_eeprom(&mut self, first_block: u16, address_bits: usize, buf: &mut [u8]) {
+        for (index, block) in buf.chunks_exact_mut(8).enumerate() {
+            let data = self.read_gba_eeprom_block(first_block + index as u16, address_bits).await;
+            block.copy_from_slice(&data.to_be_bytes());
+        }
+    }
+
+    /// Fills `buf` from the 512 B EEPROM, from block `first_block` on.
+    async fn read_gba_eeprom_512b(&mut self, first_block: u16, buf: &mut [u8]) {
+        self.read_gba_eeprom(first_block, 6, buf).await;
+    }
+
+    /// Fills `buf` from the 8 KB EEPROM, from block `first_block` on.
+    async fn read_gba_eeprom_8kb(&mut self, first_block: u16, buf: &mut [u8]) {
+        self.read_gba_eeprom(first_block, 14, buf).await;
+    }
+
+    /// Reads byte `address` of the save memory. The adapter brings /CS2 out on `refresh`, the
+    /// 16 bit save address on the word address lines and the save data on D0-D7.
+    async fn read_gba_save_byte(&mut self, address: u16) -> u8 {
+        self.set_rom16_address(address as u32);
+        self.set_refresh_low();
+        self.set_rd_low();
+        Timer::after_nanos(375).await;
+        let data = self.read_data();
+        self.set_rd_high();
+        self.set_refresh_high();
+        data
+    }
+
+    async fn write_gba_save_byte(&mut self, address: u16, data: u8) {
+        self.set_rom16_address(address as u32);
+        self.set_write_mode();
+        self.write_data(data);
+        self.set_refresh_low();
+        self.set_wr_low();
+        Timer::after_nanos(375).await;
+        self.set_wr_high();
+        self.set_refresh_high();
+        self.set_mode_read();
+    }
+
+    /// Sends a flash command behind the $5555/$2AAA unlock cycles.
+    async fn gba_flash_command(&mut self, command: u8) {
+        self.write_gba_save_byte(0x5555, 0xAA).await;
+        self.write_gba_save_byte(0x2AAA, 0x55).await;
+        self.write_gba_save_byte(0x5555, command).await;
+    }
+
+    /// Reads the manufacturer and device IDs in autoselect mode. IDs of other 64 KB chips (SST,
+    /// Panasonic, Atmel) read like the Macronix one, `None` means nothing answered.
+    async fn detect_gba_flash_chip(&mut self) -> Option<GbaFlashChip> {
+        self.gba_flash_command(0x90).await;
+        let manufacturer = self.read_gba_save_byte(0x0000).await;
+        let device = self.read_gba_save_byte(0x0001).await;
+        self.gba_flash_command(0xF0).await;
+        match (manufacturer, device) {
+            (0xC2, 0x09) => Some(GbaFlashChip::Macronix128),
+            (0x62, 0x13) => Some(GbaFlashChip::Sanyo128),
+            (0xFF, 0xFF) | (0x00, 0x00) => None,
+            _ => Some(GbaFlashChip::Macronix64),
+        }
+    }
+
+    /// Fills `buf` from the flash save at `offset`, switching the 64 KB bank of the 128 KB chips
+    /// with command 0xB0. The chip sits in read array mode, so the bytes are read directly.
+    async fn read_gba_flash_save(&mut self, chip_type: GbaFlashChip, offset: u32, buf: &mut [u8]) {
+        if chip_type != GbaFlashChip::Macronix64 && offset & 0xFFFF == 0 {
+            self.gba_flash_command(0xB0).await;
+            self.write_gba_save_byte(0x0000, (offset >> 16) as u8).await;
+        }
+        for (index, byte) in buf.iter_mut().enumerate() {
+            *byte = self.read_gba_save_byte((offset as usize + index) as u16).await;
+        }
+    }
+
+    /// Dumps the flash save of a Game Boy Advance cartridge as a `.sav` file. A chip left in an
+    /// unexpected state reads all 0xFF: the start is read again after a 0xF0 reset before going
+    /// on, a blank chip reading the same either way.
+    async fn dump_gba_flash_save(&mut self) {
+        self.setup_gba();
+        let save_type = self.detect_gba_save_type().await;
+        let chip_type = match (save_type, self.detect_gba_flash_chip().await) {
+            (GbaSaveType::Flash, Some(chip_type)) => chip_type,
+            _ => {
+                self.out_channel.send(Msg::Error { code: Msg::ERROR_GBA_NO_FLASH, context: save_type as u32 }).await;
+                self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+                self.reset_to_idle();
+                self.out_channel.send(Msg::End).await;
+                return;
+            }
+        };
+        let mut chunk = [0u8; Msg::DATA_CHANNEL_SIZE];
+        self.read_gba_flash_save(chip_type, 0, &mut chunk).await;
+        if chunk.iter().all(|byte| *byte == 0xFF) {
+            self.write_gba_save_byte(0x5555, 0xF0).await;
+        }
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: chip_type.size() }).await;
+        for offset in (0..chip_type.size()).step_by(Msg::DATA_CHANNEL_SIZE) {
+            self.read_gba_flash_save(chip_type, offset, &mut chunk).await;
+            self.buffer.copy_from_slice(&chunk);
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.reset_to_idle();
+        self.out_channel.send(Msg::End).await;
+    }
+
+    /// Dumps the EEPROM save of a Game Boy Advance cartridge, 8 bytes per block in the order the
+    /// blocks come out of the chip, as emulators load `.sav` files.
+    async fn dump_gba_eeprom(&mut self) {
+        self.setup_gba();
+        let save_type = self.detect_gba_save_type().await;
+        let size: u16 = match save_type {
+            GbaSaveType::Eeprom512 => 512,
+            GbaSaveType::Eeprom8k => 8192,
+            _ => {
+                self.out_channel.send(Msg::Error { code: Msg::ERROR_GBA_NO_EEPROM, context: save_type as u32 }).await;
+                self.out_channel.send(Msg::DumpSetupData{ rom_size: 0 }).await;
+                self.reset_to_idle();
+                self.out_channel.send(Msg::End).await;
+                return;
+            }
+        };
+        self.out_channel.send(Msg::DumpSetupData{ rom_size: size as u32 }).await;
+        let mut chunk = [0u8; Msg::DATA_CHANNEL_SIZE];
+        for offset in (0..size).step_by(Msg::DATA_CHANNEL_SIZE) {
+            let first_block = offset / 8;
+            match save_type {
+                GbaSaveType::Eeprom512 => self.read_gba_eeprom_512b(first_block, &mut chunk).await,
+                _ => self.read_gba_eeprom_8kb(first_block, &mut chunk).await,
+            }
+            self.buffer.copy_from_slice(&chunk);
+            self.send_buffer(self.buffer.len()).await;
+        }
+        self.reset_to_idle();
+        self.out_channel.send(Msg::End).await;
+    }
+}
+
+/// GPIO implementation of the NES side of the bus.
+impl<'d> BusInterface for DumperClass<'d> {
+    fn set_address(&mut self, addr: u32) {
+        if addr >= PPU_ADDRESS_SPACE {
+            self.set_ppu_address((addr - PPU_ADDRESS_SPACE) as u16);
+        } else {
+            DumperClass::set_address(self, addr as u16);
+        }
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        self.read_data()
+    }
+
+    fn write_byte(&mut self, data: u8) {
+        self.set_write_mode();
+        self.write_data(data);
+    }
+
+    fn release_data(&mut self) {
+        self.set_mode_read();
+    }
+
+    fn set_control(&mut self, signals: ControlSignals) {
+        if let Some(level) = signals.m2 {
+            self.m2.set_level(Level::from(level));
+        }
+        if let Some(level) = signals.romsel {
+            self.pgr_ce.set_level(Level::from(level));
+        }
+        if let Some(level) = signals.prg_rw {
+            self.prg_rw.set_level(Level::from(level));
+        }
+        if let Some(level) = signals.chr_rd {
+            self.chr_rd.set_level(Level::from(level));
+        }
+    }
+
+    /// The timer ticks every microsecond, so shorter waits, the read delays the latency search
+    /// tries, are busy waited in core cycles.
+    async fn wait_ns(&mut self, ns: u32) {
+        if ns >= 1000 {
+            Timer::after_nanos(ns as u64).await;
+        } else {
+            qingke::riscv::asm::delay(ns * SYSCLK_MHZ / 1000);
+        }
+    }
+}
@@ -3,32 +3,68 @@
 
 use panic_halt as _;
 use core::{cell::UnsafeCell, mem::MaybeUninit};
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
 use ch32_hal::usb::EndpointDataBuffer;
 use ch32_hal::otg_fs::{self, Driver};
 use ch32_hal::{self as hal, bind_interrupts, peripherals, Config};
-use ch32_hal::peripherals::OTG_FS;
+use ch32_hal::peripherals::{IWDG, OTG_FS};
+use ch32_hal::iwdg::IndependentWatchdog;
 use embassy_executor::{task, Spawner};
 use embassy_usb::{Builder, UsbDevice};
-use embassy_time::Timer;
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Timer};
 use embassy_sync::channel::Channel;
+use embassy_sync::blocking_mutex::Mutex;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 
 #[path = "usb/mtp.rs"]
 mod mtp;
 #[path = "dumper/dumper.rs"]
 mod dumper;
+#[path = "config/flash.rs"]
+mod flash_config;
 
-use mtp::{MtpClass, MtpContainerType};
-use dumper::{DumperClass, Msg};
+use dumper_lib::{bus, mapper_crc, rom_database};
+use mtp::{MtpClass, MtpContainerType, MtpControl, DEFAULT_IDENTITY};
+use dumper::{DumperClass, Msg, USB_CHANNEL_DEPTH, VERIFY_BUFFER_SIZE};
 
 const ENDPOINT_COUNT: usize = 14;
+/// Bulk endpoint size. OTG_FS only runs at full speed, where it must be 8/16/32/64, and the
+/// USBHS PHY pins (PB6/PB7) are taken by the cartridge bus, so 512 byte packets are not an option.
+const MAX_PACKET_SIZE: u16 = 64;
+const WATCHDOG_TIMEOUT_US: u32 = 5_000_000;
+const HEARTBEAT_PERIOD: Duration = Duration::from_secs(2);
+/// Heartbeats in a row without progress after which the MTP task abandons the command it is
+/// handling.
+const MISSED_HEARTBEATS_LIMIT: u8 = 2;
 
 bind_interrupts!(struct Irq {
     OTG_FS => otg_fs::InterruptHandler<peripherals::OTG_FS>;
 });
 
 static TO_DUMPER_CHANNEL: Channel<CriticalSectionRawMutex, Msg, 1> = Channel::new();
-static TO_USB_CHANNEL: Channel<CriticalSectionRawMutex, Msg, 1> = Channel::new();
+static TO_USB_CHANNEL: Channel<CriticalSectionRawMutex, Msg, USB_CHANNEL_DEPTH> = Channel::new();
+static HEARTBEAT_CHANNEL: Channel<CriticalSectionRawMutex, (), 1> = Channel::new();
+static MTP_BUSY: AtomicBool = AtomicBool::new(false);
+/// Set by the dumper task whenever it gets back to waiting for work, cleared by the main loop
+/// when it kicks the watchdog on its behalf.
+pub static DUMPER_ALIVE: AtomicBool = AtomicBool::new(false);
+/// Set by the MTP class whenever a bulk packet goes through or the dumper hands over a message,
+/// cleared by the MTP task on every heartbeat while it handles a command.
+pub static USB_ACTIVITY: AtomicBool = AtomicBool::new(false);
+static WATCHDOG: Mutex<CriticalSectionRawMutex, RefCell<Option<IndependentWatchdog<'static, IWDG>>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Reloads the independent watchdog, which resets the device if no task kicks it for
+/// `WATCHDOG_TIMEOUT_US`.
+pub fn kick_watchdog() {
+    WATCHDOG.lock(|watchdog| {
+        if let Some(watchdog) = watchdog.borrow_mut().as_mut() {
+            watchdog.pet();
+        }
+    });
+}
 
 // ────────────────────────────────────────────────────────────────────────────────
 // Wrapper generico: contiene un UnsafeCell ma lo dichiara Sync
@@ -52,12 +88,17 @@ impl<T> StaticCell<MaybeUninit<T>> {
 
 static EP_BUFFERS: StaticCell<MaybeUninit<[EndpointDataBuffer; ENDPOINT_COUNT]>> =
     StaticCell(UnsafeCell::new(MaybeUninit::uninit()));
+static MTP_CONTROL: StaticCell<MaybeUninit<MtpControl<'static>>> =
+    StaticCell(UnsafeCell::new(MaybeUninit::uninit()));
 static CONFIG_DESCRIPTOR        : StaticCell<[u8; 256]> = StaticCell(UnsafeCell::new([0; 256]));
 static BOS_DESCRIPTOR           : StaticCell<[u8; 256]> = StaticCell(UnsafeCell::new([0; 256]));
 static MSOS_DESCRIPTOR          : StaticCell<[u8; 256]> = StaticCell(UnsafeCell::new([0; 256]));
 static CONTROL_BUF              : StaticCell<[u8;  64]> = StaticCell(UnsafeCell::new([0;  64]));
 static DUMPER_BUF               : StaticCell<[u8;  Msg::DATA_CHANNEL_SIZE]> = StaticCell(UnsafeCell::new([0;  Msg::DATA_CHANNEL_SIZE]));
 static DUMPER_CONFIGURATION_BUF : StaticCell<[u8;1024]> = StaticCell(UnsafeCell::new([0;  1024]));
+static VERIFY_BUF               : StaticCell<[u8;  VERIFY_BUFFER_SIZE]> = StaticCell(UnsafeCell::new([0;  VERIFY_BUFFER_SIZE]));
+static ROMDB_BUF                : StaticCell<[u8; 4096]> = StaticCell(UnsafeCell::new([0; 4096]));
+static PATCHES_BUF              : StaticCell<[u8;  512]> = StaticCell(UnsafeCell::new([0;  512]));
 
 #[embassy_executor::main(entry = "qingke_rt::entry")]
 async fn main(spawner: Spawner) -> ! {
@@ -68,6 +109,10 @@ async fn main(spawner: Spawner) -> ! {
     };
     let p = hal::init(cfg);
 
+    let mut watchdog = IndependentWatchdog::new(p.IWDG, WATCHDOG_TIMEOUT_US);
+    watchdog.unleash();
+    WATCHDOG.lock(|cell| cell.replace(Some(watchdog)));
+
     let buffer = unsafe {
         EP_BUFFERS.init(core::array::from_fn(|_| EndpointDataBuffer::default()))
     };
@@ -75,9 +120,10 @@ async fn main(spawner: Spawner) -> ! {
 
     // Create embassy-usb Config
     let mut config = embassy_usb::Config::new(0x6666, 0xcafe);
-    config.manufacturer = Some("arkHive");
-    config.product = Some("MTP Dumper");
-    config.serial_number = Some("12345678");
+    let identity = DEFAULT_IDENTITY;
+    config.manufacturer = Some(identity.manufacturer);
+    config.product = Some(identity.model);
+    config.serial_number = Some(identity.serial);
     config.max_power = 100;
     config.max_packet_size_0 = 64;
 
@@ -97,8 +143,9 @@ async fn main(spawner: Spawner) -> ! {
         unsafe { &mut *CONTROL_BUF      .0.get() },
     );
 
-    // The maximum packet size MUST be 8/16/32/64 on full‑speed.
-    const MAX_PACKET_SIZE: u16 = 64;
+    // Last configuration written by the host, if any
+    let dumper_config = flash_config::read().unwrap_or_default();
+
     let dumper = DumperClass::new(
         p.PB12,
         p.PE1,
@@ -156,14 +203,21 @@ async fn main(spawner: Spawner) -> ! {
         &TO_DUMPER_CHANNEL,
         &TO_USB_CHANNEL,
         unsafe { &mut *DUMPER_BUF.0.get() },
+        unsafe { &mut *VERIFY_BUF.0.get() },
+        dumper_config,
     );
 
     let mtp_class = MtpClass::new(
         &mut builder,
         MAX_PACKET_SIZE,
+        identity,
         &TO_USB_CHANNEL,
         &TO_DUMPER_CHANNEL,
         unsafe { &mut *DUMPER_CONFIGURATION_BUF.0.get() },
+        unsafe { MTP_CONTROL.init(MtpControl::new(&MTP_BUSY)) },
+        unsafe { &mut *ROMDB_BUF.0.get() },
+        unsafe { &mut *PATCHES_BUF.0.get() },
+        dumper_config,
     );
 
     // Build the final `UsbDevice` which owns the internal state.
@@ -175,10 +229,16 @@ async fn main(spawner: Spawner) -> ! {
     spawner.spawn(mtp_task(mtp_class)).unwrap();
     spawner.spawn(usb_device_task(usb_device)).unwrap();
     spawner.spawn(rom_read_task(dumper)).unwrap();
+    spawner.spawn(heartbeat_task()).unwrap();
 
-    // The main task can now sleep forever; all work happens in the spawned tasks.
+    // All work happens in the spawned tasks. The main task only kicks the watchdog while the
+    // dumper reports itself alive, which stops as soon as the dumper is stuck, e.g. mid-dump. The
+    // liveness flag stays out of the dumper channel, where it would take the only slot.
     loop {
-        core::future::pending::<()>().await;
+        if DUMPER_ALIVE.swap(false, Ordering::Relaxed) {
+            kick_watchdog();
+        }
+        Timer::after_secs(1).await;
     }
 }
 
@@ -195,29 +255,69 @@ async fn mtp_task(mut mtp: MtpClass<'static, Driver<'static, OTG_FS, ENDPOINT_CO
     // Block until the host has configured the interface.
     mtp.wait_connection().await;
 
-    let mut buf = [0u8; 64];
+    let mut buf = [0u8; MAX_PACKET_SIZE as usize];
     loop {
-        // Read one USB bulk packet from the host.
+        // Read one USB bulk packet from the host. Waiting here for the next command is not a
+        // stall, however long the host stays idle.
         match mtp.read_packet(&mut buf).await {
             Ok(n) if n > 0 => {
                 match mtp.parse_mtp_command(&buf, MtpContainerType::Command) {
                     Ok(cmd) => {
-                        mtp.handle_response(cmd).await;
+                        if let Either::Second(_) = select(mtp.handle_response(cmd), command_stalled()).await {
+                            // The handler was dropped halfway, e.g. waiting on a data phase
+                            // the host never sends. Parks the task while the interface is
+                            // disabled, returns straight away while the host is attached.
+                            mtp.abandon_command();
+                            mtp.wait_connection().await;
+                        }
+                        kick_watchdog();
                     }
                     _ => {
                         // TODO: Handle error
                     }
                 }
             }
-            _ => {
+            Ok(_) => {
                 // Allow the USB stack some breathing room; not strictly required
                 // but avoids busy‑looping if the host stalls communication.
                 Timer::after_millis(1).await;
             }
+            Err(_) => {
+                // The interface went away, wait until the host enables it again instead of
+                // spinning on a dead endpoint
+                mtp.wait_connection().await;
+            }
         }
     }
 }
 
+/// Completes once `MISSED_HEARTBEATS_LIMIT` heartbeats in a row went by without a bulk packet
+/// or a dumper message, raced against the command handler by the MTP task.
+async fn command_stalled() {
+    // A heartbeat left over from before the command does not count against it
+    let _ = HEARTBEAT_CHANNEL.try_receive();
+    USB_ACTIVITY.store(false, Ordering::Relaxed);
+    let mut missed_heartbeats = 0;
+    while missed_heartbeats < MISSED_HEARTBEATS_LIMIT {
+        HEARTBEAT_CHANNEL.receive().await;
+        if USB_ACTIVITY.swap(false, Ordering::Relaxed) {
+            missed_heartbeats = 0;
+        } else {
+            missed_heartbeats += 1;
+        }
+    }
+}
+
+/// Sends a heartbeat to the MTP task every `HEARTBEAT_PERIOD`. The channel only holds one, so
+/// heartbeats the MTP task has not consumed yet are not queued twice.
+#[task]
+async fn heartbeat_task() {
+    loop {
+        Timer::after(HEARTBEAT_PERIOD).await;
+        let _ = HEARTBEAT_CHANNEL.try_send(());
+    }
+}
+
 #[task]
 async fn rom_read_task(mut dumper: DumperClass<'static>) {
     dumper.dump().await;
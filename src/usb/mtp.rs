@@ -1,21 +1,47 @@
 //! MTP class implementation.
 
+use core::fmt::Write;
 use core::iter;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-use embassy_time::Timer;
+use embassy_time::{with_timeout, Duration, Timer};
 use embassy_usb::driver::{Driver, Endpoint, EndpointError, EndpointIn, EndpointOut};
-use embassy_usb::{Builder};
+use embassy_usb::{Builder, Handler};
+use embassy_usb::control::{InResponse, Recipient, Request, RequestType};
 use embassy_sync::channel::Channel;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use serde::{Serialize, Deserialize};
+use heapless::String;
 
-use crate::dumper::{Msg, MsgStartConsole};
+use crate::dumper::{BusTestResult, CartridgeInfo, CartridgeStorage, DumperConfig, Msg, MsgStartConsole, MsgStartMemory, NesOutputFormat, SlotType, SnesRomType, NES_TRAINER_SIZE, QUATTRO_GAMES, QUATTRO_GAME_KB, USB_CHANNEL_DEPTH};
+use crate::flash_config;
+use crate::rom_database;
 
 /// This should be used as `device_class` when building the `UsbDevice`.
 const USB_CLASS_MTP: u8 = 0x06;
 const MTP_SUBCLASS: u8 = 0x01;
 const MTP_PROTOCOL: u8 = 0x01;
 
+/// Still Image class-specific request: Get Device Status.
+const REQ_GET_DEVICE_STATUS: u8 = 0x0C;
+
+/// Length of an event container carrying the three parameters an event can have.
+const MTP_EVENT_MAX_LENGTH: usize = 24;
+const EVENT_OBJECT_ADDED: u16 = 0x4002;
+const EVENT_OBJECT_REMOVED: u16 = 0x4003;
+const EVENT_DEVICE_PROP_CHANGED: u16 = 0x4006;
+
+const DEVICE_PROP_FUNCTIONAL_MODE: u16 = 0x5002;
+const DEVICE_PROP_DATE_TIME: u16 = 0x5011;
+const DEVICE_PROP_SYNCHRONIZATION_PARTNER: u16 = 0xD401;
+const DEVICE_PROP_FRIENDLY_NAME: u16 = 0xD402;
+/// Vendor device property: progress of the running dump in percent, `u8`.
+const DEVICE_PROP_DUMP_PROGRESS: u16 = 0x9910;
+/// Dump progress events are sent every time this many more bytes have been received.
+const DUMP_PROGRESS_STEP: u32 = 0x10000;
+/// Largest config.json checked before it replaces the current one, the size of the buffer
+/// `main` gives the class for it.
+const CONFIG_JSON_MAX_SIZE: usize = 1024;
+
 #[derive(Debug)]
 pub struct PtpCommand<'a> {
     pub op_code: u16,
@@ -27,22 +53,88 @@ pub struct PtpCommand<'a> {
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum MtpError {
     CannotParseHeader,
-    WrongPacketType
+    WrongPacketType,
+    /// The container is longer than the data received so far.
+    PayloadTruncated,
+}
+
+/// PTP operations known to the device. Operations outside this list are answered with
+/// `OperationNotSupported`.
+#[repr(u16)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MtpOpCode {
+    GetDeviceInfo = 0x1001,
+    OpenSession = 0x1002,
+    CloseSession = 0x1003,
+    GetStorageIDs = 0x1004,
+    GetStorageInfo = 0x1005,
+    GetNumObjects = 0x1006,
+    GetObjectHandles = 0x1007,
+    GetObjectInfo = 0x1008,
+    GetObject = 0x1009,
+    GetThumb = 0x100A,
+    DeleteObject = 0x100B,
+    SendObjectInfo = 0x100C,
+    SendObject = 0x100D,
+    InitiateCapture = 0x100E,
+    FormatStore = 0x100F,
+    ResetDevice = 0x1010,
+    GetDevicePropDesc = 0x1014,
+    GetDevicePropValue = 0x1015,
+    TruncateObject = 0x95C3,
+    BeginEditObject = 0x95C4,
+    EndEditObject = 0x95C5,
+}
+
+impl TryFrom<u16> for MtpOpCode {
+    type Error = MtpCommandError;
+
+    fn try_from(op_code: u16) -> Result<Self, Self::Error> {
+        match op_code {
+            0x1001 => Ok(MtpOpCode::GetDeviceInfo),
+            0x1002 => Ok(MtpOpCode::OpenSession),
+            0x1003 => Ok(MtpOpCode::CloseSession),
+            0x1004 => Ok(MtpOpCode::GetStorageIDs),
+            0x1005 => Ok(MtpOpCode::GetStorageInfo),
+            0x1006 => Ok(MtpOpCode::GetNumObjects),
+            0x1007 => Ok(MtpOpCode::GetObjectHandles),
+            0x1008 => Ok(MtpOpCode::GetObjectInfo),
+            0x1009 => Ok(MtpOpCode::GetObject),
+            0x100A => Ok(MtpOpCode::GetThumb),
+            0x100B => Ok(MtpOpCode::DeleteObject),
+            0x100C => Ok(MtpOpCode::SendObjectInfo),
+            0x100D => Ok(MtpOpCode::SendObject),
+            0x100E => Ok(MtpOpCode::InitiateCapture),
+            0x100F => Ok(MtpOpCode::FormatStore),
+            0x1010 => Ok(MtpOpCode::ResetDevice),
+            0x1014 => Ok(MtpOpCode::GetDevicePropDesc),
+            0x1015 => Ok(MtpOpCode::GetDevicePropValue),
+            0x95C3 => Ok(MtpOpCode::TruncateObject),
+            0x95C4 => Ok(MtpOpCode::BeginEditObject),
+            0x95C5 => Ok(MtpOpCode::EndEditObject),
+            _ => Err(MtpCommandError::OperationNotSupported),
+        }
+    }
 }
 
 #[repr(u16)]
-enum MtpCommandError {
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MtpCommandError {
     Ok = 0x2001,
     // SessionNotOpen = 0x2003,
     // InvalidTransactionId = 0x2004,
     OperationNotSupported = 0x2005,
     // ParameterNotSupported = 0x2006,
     // InvalidStorageId = 0x2008,
+    InvalidObjectHandle = 0x2009,
+    DevicePropNotSupported = 0x200A,
     InvalidObjectFormatCode = 0x200B,
     // StoreFull = 0x200C,
+    ObjectWriteProtected = 0x200D,
     // StoreReadOnly = 0x200E,
     // AccessDenied = 0x200F,
     StoreNotAvailable = 0x2013,
+    DeviceBusy = 0x2019,
     InvalidParentObject = 0x201A,
     ObjectTooLarge = 0xA809,
 }
@@ -53,16 +145,415 @@ pub enum MtpContainerType {
     Command = 0x0001,
     Data = 0x0002,
     Response = 0x0003,
-    // Event = 0x0004,
+    Event = 0x0004,
+}
+
+/// Strings reported to the host in the MTP DeviceInfo dataset and the USB device descriptor.
+#[derive(Copy, Clone, Debug)]
+pub struct DeviceIdentity {
+    pub manufacturer: &'static str,
+    pub model: &'static str,
+    pub version: &'static str,
+    pub serial: &'static str,
+    pub vendor_ext: &'static str,
+}
+
+pub const DEFAULT_IDENTITY: DeviceIdentity = DeviceIdentity {
+    manufacturer: "arkHive",
+    model: "MTP Dumper",
+    version: "1.0",
+    serial: "12345678",
+    vendor_ext: "microsoft.com: 1.0",
+};
+
+/// Handle of the ROM database uploaded by the host, see [`UploadedObject`].
+pub const ROMDB_HANDLE: u32 = 0x0000000D;
+/// Handle of the `[address, value]` PRG patches uploaded by the host, see [`UploadedObject`].
+pub const PATCHES_HANDLE: u32 = 0x0000000E;
+/// Handle of the NES battery backed SRAM, readable and writable by the host.
+pub const SRAM_HANDLE: u32 = 0x00000013;
+const SRAM_SIZE: usize = 0x2000;
+/// Handle of the NES PRG-RAM, banked through the mapper when it supports it.
+pub const PRG_RAM_HANDLE: u32 = 0x00000009;
+/// Handle of the VRC7 instrument patches, empty when the chip does not give them out.
+pub const VRC7_PATCHES_HANDLE: u32 = 0x0000000F;
+/// Handle of the Namco 163 sound RAM. It is volatile, so the dump is its state after power-on,
+/// not a fixed ROM content.
+pub const NAMCO163_RAM_HANDLE: u32 = 0x00000010;
+/// Handle of the serial EEPROM of Bandai FCG boards, sized by `bandai_eeprom_type`.
+pub const BANDAI_EEPROM_HANDLE: u32 = 0x00000011;
+/// Handle of game1.nes on a Camerica Quattro cartridge, the other games follow.
+pub const QUATTRO_GAME_HANDLE: u32 = 0x00000027;
+const QUATTRO_LAST_GAME_HANDLE: u32 = QUATTRO_GAME_HANDLE + QUATTRO_GAMES as u32 - 1;
+const QUATTRO_GAME_NAMES: [&str; QUATTRO_GAMES as usize] = ["game1.nes", "game2.nes", "game3.nes", "game4.nes"];
+/// Handle of the headerless PRG-ROM dump of the NES cartridge.
+pub const NES_RAW_PRG_HANDLE: u32 = 0x00000023;
+/// Handle of the NES cartridge dump behind a NES 2.0 header.
+pub const NES2_ROM_HANDLE: u32 = 0x00000024;
+/// Handle of the flash save of a Game Boy Advance cartridge, in the GBA folder.
+pub const GBA_FLASH_HANDLE: u32 = 0x0000002B;
+/// Handle of the PRG and CHR CRCs of the last NES dump, as NES Cart DB lists them.
+pub const CHECKSUMS_HANDLE: u32 = 0x00000014;
+/// Handle of the first 32 KB LoROM bank of the SNES cartridge, to check the game before a full
+/// dump.
+pub const SNES_PARTIAL_HANDLE: u32 = 0x00000015;
+/// Handle of the MMC3 work RAM, volatile like the Namco 163 RAM.
+pub const WRAM_HANDLE: u32 = 0x00000012;
+/// Handle of the 1 KB MMC5 ExRAM, empty on other mappers.
+pub const EXRAM_HANDLE: u32 = 0x00000016;
+/// Handle of the log of the last dump, one line per data chunk.
+pub const DUMP_LOG_HANDLE: u32 = 0x00000017;
+const DUMP_LOG_SIZE: usize = 512;
+/// Handle of the combined NES + SNES dump of a dual slot adapter.
+pub const MULTI_DUMP_HANDLE: u32 = 0x0000000A;
+/// Largest dump announced with its size, the 32 MB of the biggest N64 cartridges. Anything bigger
+/// is a misconfiguration.
+const MAX_ROM_SIZE: u32 = 32 * 1024 * 1024;
+
+/// Object listed by GetObjectHandles. `parent` is 0 for the root, as in the ObjectInfo dataset.
+struct ObjectEntry {
+    handle: u32,
+    parent: u32,
+    format: u16,
+}
+
+impl ObjectEntry {
+    const fn folder(handle: u32) -> Self {
+        ObjectEntry { handle, parent: 0, format: 0x3001 }
+    }
+
+    const fn file(handle: u32, parent: u32) -> Self {
+        ObjectEntry { handle, parent, format: 0x3000 }
+    }
+}
+
+/// Every object the device can list, see [`MtpClass::object_present`] for the ones that come
+/// and go.
+const OBJECT_REGISTRY: [ObjectEntry; 42] = [
+    ObjectEntry::folder(0x00000001),
+    ObjectEntry::folder(0x00000004),
+    ObjectEntry::folder(0x00000006),
+    ObjectEntry::folder(0x00000018),
+    ObjectEntry::folder(0x0000001A),
+    ObjectEntry::folder(0x0000001D),
+    ObjectEntry::folder(0x0000001F),
+    ObjectEntry::folder(0x00000021),
+    ObjectEntry::folder(0x00000025),
+    ObjectEntry::file(0x00000002, 0x00000001),
+    ObjectEntry::file(NES_RAW_PRG_HANDLE, 0x00000001),
+    ObjectEntry::file(NES2_ROM_HANDLE, 0x00000001),
+    ObjectEntry::file(0x00000003, 0x00000001),
+    ObjectEntry::file(0x0000000B, 0x00000001),
+    ObjectEntry::file(CHECKSUMS_HANDLE, 0x00000001),
+    ObjectEntry::file(SRAM_HANDLE, 0x00000001),
+    ObjectEntry::file(PRG_RAM_HANDLE, 0x00000001),
+    ObjectEntry::file(VRC7_PATCHES_HANDLE, 0x00000001),
+    ObjectEntry::file(NAMCO163_RAM_HANDLE, 0x00000001),
+    ObjectEntry::file(BANDAI_EEPROM_HANDLE, 0x00000001),
+    ObjectEntry::file(WRAM_HANDLE, 0x00000001),
+    ObjectEntry::file(EXRAM_HANDLE, 0x00000001),
+    ObjectEntry::file(QUATTRO_GAME_HANDLE, 0x00000001),
+    ObjectEntry::file(QUATTRO_GAME_HANDLE + 1, 0x00000001),
+    ObjectEntry::file(QUATTRO_GAME_HANDLE + 2, 0x00000001),
+    ObjectEntry::file(QUATTRO_GAME_HANDLE + 3, 0x00000001),
+    ObjectEntry::file(0x00000005, 0x00000004),
+    ObjectEntry::file(SNES_PARTIAL_HANDLE, 0x00000004),
+    ObjectEntry::file(0x00000007, 0x00000006),
+    ObjectEntry::file(0x0000001B, 0x0000001A),
+    ObjectEntry::file(0x0000001E, 0x0000001D),
+    ObjectEntry::file(0x00000022, 0x00000021),
+    ObjectEntry::file(0x00000026, 0x00000025),
+    ObjectEntry::file(0x00000020, 0x0000001F),
+    ObjectEntry::file(GBA_FLASH_HANDLE, 0x0000001F),
+    ObjectEntry::file(0x00000019, 0x00000018),
+    ObjectEntry::file(0x0000001C, 0x00000018),
+    ObjectEntry::file(0x00000008, 0),
+    ObjectEntry::file(DUMP_LOG_HANDLE, 0),
+    ObjectEntry::file(MULTI_DUMP_HANDLE, 0),
+    ObjectEntry::file(ROMDB_HANDLE, 0),
+    ObjectEntry::file(PATCHES_HANDLE, 0),
+];
+
+/// Maximum number of patches accepted in a single patches.json.
+const MAX_PATCHES: usize = 32;
+
+/// Binary object the host can store on the device with SendObjectInfo/SendObject.
+///
+/// Uploaded objects live in the root folder and are only listed once they hold some data.
+pub struct UploadedObject<'d> {
+    pub handle: u32,
+    pub name: &'static str,
+    pub data: &'d mut [u8],
+    pub size: usize,
+}
+
+impl<'d> UploadedObject<'d> {
+    pub fn new(handle: u32, name: &'static str, data: &'d mut [u8]) -> Self {
+        UploadedObject {
+            handle,
+            name,
+            data,
+            size: 0,
+        }
+    }
+}
+
+/// How the length of a dump is announced in its data container.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MtpTransferMode {
+    KnownSize(u32),
+    /// The container length is 0xFFFFFFFF and the host reads until the short packet or ZLP that
+    /// ends the transfer.
+    ChunkedUnknown,
+}
+
+/// Text log of the chunks received from the dumper, `offset=XXXXXXXX bytes=NN` per line. When it
+/// is full the oldest lines are dropped.
+struct DumpLog {
+    text: [u8; DUMP_LOG_SIZE],
+    fill: usize,
+    offset: u32,
+}
+
+impl DumpLog {
+    const fn new() -> Self {
+        DumpLog {
+            text: [0; DUMP_LOG_SIZE],
+            fill: 0,
+            offset: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.fill = 0;
+        self.offset = 0;
+    }
+
+    fn push_chunk(&mut self, length: usize) {
+        let mut line: String<32> = String::new();
+        let _ = writeln!(line, "offset={:08X} bytes={:02}", self.offset, length);
+        self.offset += length as u32;
+        while self.fill + line.len() > self.text.len() {
+            let first_line = match self.text[..self.fill].iter().position(|c| *c == b'\n') {
+                Some(end) => end + 1,
+                None => self.fill,
+            };
+            self.text.copy_within(first_line..self.fill, 0);
+            self.fill -= first_line;
+        }
+        self.text[self.fill..self.fill + line.len()].copy_from_slice(line.as_bytes());
+        self.fill += line.len();
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.text[..self.fill]
+    }
+}
+
+/// Progress of the running dump, reported through [`DEVICE_PROP_DUMP_PROGRESS`].
+struct DumpProgress {
+    handle: u32,
+    transaction_id: u32,
+    total: u32,
+    received: u32,
+    percent: u8,
+}
+
+impl DumpProgress {
+    const fn new() -> Self {
+        DumpProgress {
+            handle: 0,
+            transaction_id: 0,
+            total: 0,
+            received: 0,
+            percent: 0,
+        }
+    }
+
+    fn start(&mut self, handle: u32, transaction_id: u32) {
+        *self = DumpProgress { handle, transaction_id, ..DumpProgress::new() };
+    }
+
+    /// Counts `length` more bytes, returning `true` when a [`DUMP_PROGRESS_STEP`] boundary has
+    /// been crossed and the host should be told.
+    fn advance(&mut self, length: usize) -> bool {
+        let previous = self.received;
+        self.received += length as u32;
+        if self.total > 0 {
+            self.percent = core::cmp::min(self.received as u64 * 100 / self.total as u64, 100) as u8;
+        }
+        previous / DUMP_PROGRESS_STEP != self.received / DUMP_PROGRESS_STEP
+    }
+}
+
+/// Where [`strip_json_comments`] is in its input.
+enum JsonScanState {
+    Normal,
+    InString,
+    /// After a backslash inside a string.
+    StringEscape,
+    LineComment,
+    BlockComment,
+}
+
+/// Copies the JSON `input` to `output` without its `//` and `/* */` comments, which
+/// serde-json-core does not accept. Comment markers inside strings are kept. Returns the length
+/// written, the output being cut short when it does not fit.
+fn strip_json_comments(input: &[u8], output: &mut [u8]) -> usize {
+    let mut state = JsonScanState::Normal;
+    let mut length = 0;
+    let mut index = 0;
+    while index < input.len() && length < output.len() {
+        let byte = input[index];
+        let next = input.get(index + 1).copied();
+        index += 1;
+        let kept = match state {
+            JsonScanState::Normal => match (byte, next) {
+                (b'/', Some(b'/')) => {
+                    state = JsonScanState::LineComment;
+                    index += 1;
+                    None
+                }
+                (b'/', Some(b'*')) => {
+                    state = JsonScanState::BlockComment;
+                    index += 1;
+                    None
+                }
+                (b'"', _) => {
+                    state = JsonScanState::InString;
+                    Some(byte)
+                }
+                _ => Some(byte),
+            },
+            JsonScanState::InString => {
+                match byte {
+                    b'\\' => state = JsonScanState::StringEscape,
+                    b'"' => state = JsonScanState::Normal,
+                    _ => {}
+                }
+                Some(byte)
+            }
+            JsonScanState::StringEscape => {
+                state = JsonScanState::InString;
+                Some(byte)
+            }
+            JsonScanState::LineComment => {
+                // The line break is kept, the comment may have been the only separator
+                if byte == b'\n' {
+                    state = JsonScanState::Normal;
+                    Some(byte)
+                } else {
+                    None
+                }
+            }
+            JsonScanState::BlockComment => {
+                if byte == b'*' && next == Some(b'/') {
+                    state = JsonScanState::Normal;
+                    index += 1;
+                    Some(b' ')
+                } else {
+                    None
+                }
+            }
+        };
+        if let Some(byte) = kept {
+            output[length] = byte;
+            length += 1;
+        }
+    }
+    length
+}
+
+/// What [`MtpClass::next_dump_event`] stopped streaming for.
+enum DumpEvent {
+    /// The dumper announced the size of the dump that follows.
+    Setup(u32),
+    /// The dump is complete.
+    End,
+    /// The host stopped reading, the transfer is abandoned.
+    Stalled,
+}
+
+/// Size of the [`CombinedDumpFormat`] manifest ahead of the ROMs in `multi-dump.bin`.
+const COMBINED_DUMP_HEADER_SIZE: usize = 256;
+
+/// Manifest opening `multi-dump.bin`: the NES ROM (iNES, header included) follows it and the SNES
+/// ROM follows the NES one, running to the end of the file. All fields are little endian, the rest
+/// of the 256 bytes is zero.
+pub struct CombinedDumpFormat {
+    pub nes_offset: u32,
+    pub nes_size: u32,
+    pub snes_offset: u32,
+}
+
+impl CombinedDumpFormat {
+    const MAGIC: [u8; 8] = *b"WCHMULTI";
+    const VERSION: u32 = 1;
+
+    fn new(nes_size: u32) -> Self {
+        CombinedDumpFormat {
+            nes_offset: COMBINED_DUMP_HEADER_SIZE as u32,
+            nes_size,
+            snes_offset: COMBINED_DUMP_HEADER_SIZE as u32 + nes_size,
+        }
+    }
+
+    fn to_bytes(&self) -> [u8; COMBINED_DUMP_HEADER_SIZE] {
+        let mut bytes = [0u8; COMBINED_DUMP_HEADER_SIZE];
+        bytes[0..8].copy_from_slice(&Self::MAGIC);
+        bytes[8..12].copy_from_slice(&Self::VERSION.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.nes_offset.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.nes_size.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.snes_offset.to_le_bytes());
+        bytes
+    }
+}
+
+impl MtpTransferMode {
+    fn container_length(&self) -> u32 {
+        match self {
+            MtpTransferMode::KnownSize(size) => size + 12,
+            MtpTransferMode::ChunkedUnknown => 0xFFFFFFFF,
+        }
+    }
+}
+
+/// Handles the class-specific requests the host sends to the MTP interface on the control pipe.
+pub struct MtpControl<'d> {
+    interface_number: u16,
+    busy: &'d AtomicBool,
+}
+
+impl<'d> MtpControl<'d> {
+    pub fn new(busy: &'d AtomicBool) -> Self {
+        MtpControl {
+            interface_number: 0,
+            busy,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct DumperConfig {
-    pub mapper: u8,
-    pub prgsize: u8,
-    pub chrsize: u8,
-    pub prg: u16, // KB
-    pub chr: u16, // KB
+impl<'d> Handler for MtpControl<'d> {
+    fn control_in<'a>(&'a mut self, req: Request, buf: &'a mut [u8]) -> Option<InResponse<'a>> {
+        if req.request_type != RequestType::Class
+            || req.recipient != Recipient::Interface
+            || req.index != self.interface_number {
+            return None;
+        }
+        match req.request {
+            REQ_GET_DEVICE_STATUS => {
+                let code = if self.busy.load(Ordering::Relaxed) {
+                    MtpCommandError::DeviceBusy
+                } else {
+                    MtpCommandError::Ok
+                };
+                buf[0..2].copy_from_slice(&4u16.to_le_bytes()); // wLength
+                buf[2..4].copy_from_slice(&(code as u16).to_le_bytes()); // Code
+                Some(InResponse::Accepted(&buf[..4]))
+            }
+            _ => Some(InResponse::Rejected),
+        }
+    }
 }
 
 /// Packet level implementation of a MTP serial port.
@@ -78,14 +569,36 @@ pub struct DumperConfig {
 ///   can be sent if there is no other data to send. This is because USB bulk transactions must be
 ///   terminated with a short packet, even if the bulk endpoint is used for stream-like data.
 pub struct MtpClass<'d, D: Driver<'d>> {
-    //_comm_ep: D::EndpointIn,
+    comm_ep: D::EndpointIn,
     read_ep: D::EndpointOut,
     write_ep: D::EndpointIn,
-    in_channel: &'d Channel<CriticalSectionRawMutex, Msg, 1>,
+    identity: DeviceIdentity,
+    in_channel: &'d Channel<CriticalSectionRawMutex, Msg, USB_CHANNEL_DEPTH>,
     out_channel: &'d Channel<CriticalSectionRawMutex, Msg, 1>,
     configuration_file: &'d mut [u8],
     configuration_file_size: usize,
     configuration_file_deleted: bool,
+    /// config.json is being edited: uploads are kept until EndEditObject applies them.
+    edit_lock: bool,
+    busy: &'d AtomicBool,
+    verify_result: Option<(u32, u32)>,
+    /// `(passed, expected, actual)` of the last known CRC check, CRCs as `(prg, chr)`.
+    crc_check: Option<(bool, (u32, u32), (u32, u32))>,
+    prg_crc32: Option<u32>,
+    chr_crc32: Option<u32>,
+    /// Sizes announced by the last rom.nes and rom.sfc dumps, `None` until one has run.
+    nes_rom_size: Option<u32>,
+    snes_rom_size: Option<u32>,
+    cartridge_info: CartridgeInfo,
+    last_error: Option<(u8, u32)>,
+    last_warning: Option<u8>,
+    uploads: [UploadedObject<'d>; 2],
+    pending_upload: u32,
+    sent_object: Option<u32>,
+    dump_log: DumpLog,
+    dump_progress: DumpProgress,
+    /// Last configuration sent to the dumper, for the objects that depend on it.
+    dumper_config: DumperConfig,
 }
 
 impl<'d, D: Driver<'d>> MtpClass<'d, D> {
@@ -93,39 +606,84 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
     /// full-speed devices, `max_packet_size` has to be one of 8, 16, 32 or 64.
     pub fn new(builder: &mut Builder<'d, D>,
         max_packet_size: u16,
-        in_channel: &'d Channel<CriticalSectionRawMutex, Msg, 1>,
+        identity: DeviceIdentity,
+        in_channel: &'d Channel<CriticalSectionRawMutex, Msg, USB_CHANNEL_DEPTH>,
         out_channel: &'d Channel<CriticalSectionRawMutex, Msg, 1>,
-        configuration_file: &'d mut [u8]) -> Self {
+        configuration_file: &'d mut [u8],
+        control: &'d mut MtpControl<'d>,
+        romdb_buffer: &'d mut [u8],
+        patches_buffer: &'d mut [u8],
+        config: DumperConfig) -> Self {
         assert!(builder.control_buf_len() >= 7);
 
         let mut func = builder.function(0x00, 0x00, 0x00);
         let mut iface = func.interface();
+        control.interface_number = u8::from(iface.interface_number()) as u16;
         let mut alt = iface.alt_setting(USB_CLASS_MTP, MTP_SUBCLASS, MTP_PROTOCOL, None);
         let read_ep = alt.endpoint_bulk_out(max_packet_size);
         let write_ep = alt.endpoint_bulk_in(max_packet_size);
-        //let comm_ep = alt.endpoint_interrupt_in(8, 255);
+        let comm_ep = alt.endpoint_interrupt_in(MTP_EVENT_MAX_LENGTH as u16, 10);
 
         drop(func);
 
-        let config = DumperConfig {
-            mapper: 1,
-            prgsize: 3,
-            chrsize: 0,
-            prg: 128,
-            chr: 0
-        };
+        let busy = control.busy;
+        builder.handler(control);
 
         let configuration_file_size = serde_json_core::to_slice(&config, configuration_file).unwrap();
         MtpClass {
-            //_comm_ep: comm_ep,
+            comm_ep,
             read_ep,
             write_ep,
+            identity,
             in_channel,
             out_channel,
             configuration_file,
             configuration_file_size,
             configuration_file_deleted: false,
+            edit_lock: false,
+            busy,
+            verify_result: None,
+            crc_check: None,
+            prg_crc32: None,
+            chr_crc32: None,
+            nes_rom_size: None,
+            snes_rom_size: None,
+            cartridge_info: CartridgeInfo::default(),
+            last_error: None,
+            last_warning: None,
+            uploads: [
+                UploadedObject::new(ROMDB_HANDLE, "romdb.bin", romdb_buffer),
+                UploadedObject::new(PATCHES_HANDLE, "patches.json", patches_buffer),
+            ],
+            pending_upload: 0x00000003,
+            sent_object: None,
+            dump_log: DumpLog::new(),
+            dump_progress: DumpProgress::new(),
+            dumper_config: config,
+        }
+    }
+
+    /// Sends an MTP event with a single parameter on the interrupt endpoint.
+    pub async fn send_event(&mut self, code: u16, transaction_id: u32, param: u32) {
+        self.send_event_params(code, transaction_id, &[param]).await;
+    }
+
+    /// Sends an MTP event with up to three parameters on the interrupt endpoint.
+    ///
+    /// The write is abandoned if the host is not polling the endpoint, so a missing event never
+    /// stalls the command loop.
+    pub async fn send_event_params(&mut self, code: u16, transaction_id: u32, params: &[u32]) {
+        let mut buffer = [0u8; MTP_EVENT_MAX_LENGTH];
+        let mut offset = 0;
+        let length = 12 + 4 * params.len();
+        Self::write_u32(&mut buffer, &mut offset, length as u32);
+        Self::write_u16(&mut buffer, &mut offset, MtpContainerType::Event as u16);
+        Self::write_u16(&mut buffer, &mut offset, code);
+        Self::write_u32(&mut buffer, &mut offset, transaction_id);
+        for param in params {
+            Self::write_u32(&mut buffer, &mut offset, *param);
         }
+        let _ = with_timeout(Duration::from_millis(100), self.comm_ep.write(&buffer[..length])).await;
     }
 
     /// Gets the maximum packet size in bytes.
@@ -137,12 +695,26 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
     /// Writes a single packet into the IN endpoint.
     pub async fn write_packet(&mut self, data: &[u8]) -> Result<(), EndpointError> {
         let len = core::cmp::min(data.len(), self.max_packet_size() as usize);
-        self.write_ep.write(&data[..len]).await
+        let result = self.write_ep.write(&data[..len]).await;
+        if result.is_ok() {
+            crate::USB_ACTIVITY.store(true, Ordering::Relaxed);
+        }
+        result
     }
 
     /// Reads a single packet from the OUT endpoint.
     pub async fn read_packet(&mut self, data: &mut [u8]) -> Result<usize, EndpointError> {
-        self.read_ep.read(data).await
+        let result = self.read_ep.read(data).await;
+        if result.is_ok() {
+            crate::USB_ACTIVITY.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Clears what a command dropped halfway by the MTP task leaves behind, so the host is no
+    /// longer answered DeviceBusy.
+    pub fn abandon_command(&mut self) {
+        self.busy.store(false, Ordering::Relaxed);
     }
 
     /// Waits for the USB host to enable this interface
@@ -151,10 +723,16 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
     }
 
     pub fn parse_mtp_command<'a>(&self, buf: &'a[u8], phase: MtpContainerType) -> Result<PtpCommand<'a>, MtpError> {
+        if buf.len() < 12 {
+            return Err(MtpError::CannotParseHeader);
+        }
         let length = usize::from_le_bytes(buf[0..4].try_into().unwrap());
         if length < 12 {
             return Err(MtpError::CannotParseHeader);
         }
+        if length > buf.len() {
+            return Err(MtpError::PayloadTruncated);
+        }
         let packet_type = u16::from_le_bytes(buf[4..6].try_into().unwrap());
         let op_code = u16::from_le_bytes(buf[6..8].try_into().unwrap());
         let transaction_id = u32::from_le_bytes(buf[8..12].try_into().unwrap());
@@ -248,13 +826,14 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
         Self::write_u16(buffer, &mut offset, 110); // StandardVersion
         Self::write_u32(buffer, &mut offset, 6); // VendorExtensionID = 6 (Microsoft)
         Self::write_u16(buffer, &mut offset, 100);  // VendorExtensionVersion
-        Self::write_string(buffer, &mut offset, "microsoft.com: 1.0"); // VendorExtensionDesc
+        Self::write_string(buffer, &mut offset, self.identity.vendor_ext); // VendorExtensionDesc
         Self::write_u16(buffer, &mut offset, 0); // FunctionalMode
         let supported_operations = [
             0x1001, 0x1002, 0x1003, 0x1004, 0x1005, 0x1006, 0x1007, 0x1008, 0x1009, 0x100A,
             0x100B, 0x100C, 0x100D, 0x100E, 0x100F, 0x1010, 0x1011, 0x1012, 0x1013, 0x1014,
             0x1015, 0x1016, 0x1017, 0x1018, 0x1019, 0x101A, 0x101B, 0x101C, 0x9801, 0x9802,
             0x9803, 0x9804, 0x9810, 0x9811, 0x9820, 0x9805, 0x9806, 0x9807, 0x9808,
+            0x95C3, 0x95C4, 0x95C5,
         ];
         Self::write_u32(buffer, &mut offset, supported_operations.len().try_into().unwrap()); // NumOperationsSupported
         for operation in supported_operations  {
@@ -269,7 +848,8 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
             Self::write_u16(buffer, &mut offset, event); // EventSupported
         }
         let supported_device_properties = [
-            0xd401, 0xd402, 0x5002, 0x5011,
+            DEVICE_PROP_SYNCHRONIZATION_PARTNER, DEVICE_PROP_FRIENDLY_NAME, DEVICE_PROP_FUNCTIONAL_MODE,
+            DEVICE_PROP_DATE_TIME, DEVICE_PROP_DUMP_PROGRESS,
         ];
         Self::write_u32(buffer, &mut offset, supported_device_properties.len().try_into().unwrap()); // NumDevicePropertiesSupported
         for device_property in supported_device_properties  {
@@ -285,10 +865,10 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
         for playback in supported_playbacks  {
             Self::write_u16(buffer, &mut offset, playback); // PlaybackSupported
         }
-        Self::write_string(buffer, &mut offset, "arkHive"); // Manufacturer
-        Self::write_string(buffer, &mut offset, "MTP Dumper"); // Model
-        Self::write_string(buffer, &mut offset, "1.0"); // DeviceVersion
-        Self::write_string(buffer, &mut offset, "12345678"); // SerialNumber
+        Self::write_string(buffer, &mut offset, self.identity.manufacturer); // Manufacturer
+        Self::write_string(buffer, &mut offset, self.identity.model); // Model
+        Self::write_string(buffer, &mut offset, self.identity.version); // DeviceVersion
+        Self::write_string(buffer, &mut offset, self.identity.serial); // SerialNumber
         let total_len = offset as u32;
         Self::write_u32(buffer, &mut 0, total_len);
         Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
@@ -336,72 +916,72 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
         offset
     }
 
-    fn object_format_codes_contains(cmd: &PtpCommand, needle: u16) -> bool {
-        let object_format_code_count= u32::from_le_bytes(cmd.payload[4..8].try_into().unwrap());
-        if object_format_code_count == 0 {
-            return true;
-        }
-        let object_format_code_offset = 8;
-        for object_format_code_index in 0..object_format_code_count {
-            let buffer_index = object_format_code_offset + (object_format_code_index * 2) as usize;
-            let object_format_code = u16::from_le_bytes([cmd.payload[buffer_index], cmd.payload[buffer_index+1]]);
-            if object_format_code == needle {
-                return true;
-            }
+    /// Reads the `index`-th 32 bit parameter of a command, hosts may omit trailing parameters.
+    fn command_parameter(cmd: &PtpCommand, index: usize) -> u32 {
+        match cmd.payload.get(index * 4..index * 4 + 4) {
+            Some(parameter) => u32::from_le_bytes(parameter.try_into().unwrap()),
+            None => 0,
         }
-        return false;
     }
 
+    /// GetObjectHandles filters on a single ObjectFormatCode (second parameter), 0 meaning all
+    /// formats.
+    fn object_format_codes_contains(cmd: &PtpCommand, needle: u16) -> bool {
+        let object_format_code = Self::command_parameter(cmd, 1);
+        object_format_code == 0 || object_format_code == needle as u32
+    }
+
+    /// GetObjectHandles filters on the parent association (third parameter), 0 meaning all
+    /// objects and 0xFFFFFFFF the root. `needle` is the parent of the object, 0 for the root.
     fn object_handle_of_association_contains(cmd: &PtpCommand, needle: u32) -> bool {
-        let object_format_code_count= u32::from_le_bytes(cmd.payload[4..8].try_into().unwrap());
-        let object_format_code_offset = 8;
-        let object_handle_of_association_offset = object_format_code_offset + (object_format_code_count * 2) as usize;
-        let object_handle_of_association= u32::from_le_bytes(cmd.payload[
-            object_handle_of_association_offset..object_handle_of_association_offset+4
-            ].try_into().unwrap());
+        let object_handle_of_association = Self::command_parameter(cmd, 2);
         if object_handle_of_association == 0 {
             return true;
         }
+        if object_handle_of_association == 0xFFFFFFFF {
+            return needle == 0;
+        }
         return needle == object_handle_of_association;
     }
 
+    /// Handles of the game objects, listed when the configuration is a 256 KB Camerica Quattro.
+    fn quattro_game_handles(&self) -> Option<core::ops::Range<u32>> {
+        if self.dumper_config.mapper == 232 && self.dumper_config.prg == QUATTRO_GAMES * QUATTRO_GAME_KB {
+            Some(QUATTRO_GAME_HANDLE..QUATTRO_GAME_HANDLE + QUATTRO_GAMES as u32)
+        } else {
+            None
+        }
+    }
+
+    /// Whether an object of [`OBJECT_REGISTRY`] currently exists: config.json until deleted, the
+    /// uploads once they hold data and the Quattro games with a matching configuration.
+    fn object_present(&self, handle: u32) -> bool {
+        match handle {
+            0x00000003 => !self.configuration_file_deleted,
+            QUATTRO_GAME_HANDLE..=QUATTRO_LAST_GAME_HANDLE => self.quattro_game_handles().is_some_and(|games| games.contains(&handle)),
+            _ => self.uploads.iter()
+                .find(|upload| upload.handle == handle)
+                .is_none_or(|upload| upload.size > 0),
+        }
+    }
+
+    /// Lists the objects of [`OBJECT_REGISTRY`] passing the three GetObjectHandles filters:
+    /// StorageID (0xFFFFFFFF for all storages), ObjectFormatCode and parent association.
     fn generate_object_handles_response<'a>(&self, transaction_id: u32, buffer: &mut [u8], cmd: &PtpCommand<'a>) -> usize {
         let mut offset = 12;
         let storage_id= u32::from_le_bytes(cmd.payload[0..4].try_into().unwrap());
         let mut object_handle_offset = offset;
         offset += 4;
         let mut object_handle_count = 0;
-        if (storage_id == 0xFFFFFFFF || storage_id == 0x00010001) &&
-            Self::object_format_codes_contains(cmd, 0x3001) &&
-            Self::object_handle_of_association_contains(cmd, 0xFFFFFFFF) {
-                let handles = [
-                    0x00000001,
-                    0x00000004,
-                    0x00000006,
-                ];
-                for handle in handles.iter() {
-                    Self::write_u32(buffer, &mut offset, *handle); // ObjectHandle[0] id
-                }
-                object_handle_count += handles.len() as u32;
-        }
-        if (storage_id == 0xFFFFFFFF || storage_id == 0x00010001) &&
-            Self::object_format_codes_contains(cmd, 0x3000) {
-            if Self::object_handle_of_association_contains(cmd, 0x00000001) {
-                Self::write_u32(buffer, &mut offset, 0x00000002); // ObjectHandle[0] id
-                object_handle_count += 1;
-                if !self.configuration_file_deleted {
-                    Self::write_u32(buffer, &mut offset, 0x00000003); // ObjectHandle[0] id
+        if storage_id == 0xFFFFFFFF || storage_id == 0x00010001 {
+            for object in OBJECT_REGISTRY.iter() {
+                if Self::object_format_codes_contains(cmd, object.format) &&
+                    Self::object_handle_of_association_contains(cmd, object.parent) &&
+                    self.object_present(object.handle) {
+                    Self::write_u32(buffer, &mut offset, object.handle); // ObjectHandle[0] id
                     object_handle_count += 1;
                 }
             }
-            if Self::object_handle_of_association_contains(cmd, 0x00000004) {
-                Self::write_u32(buffer, &mut offset, 0x00000005); // ObjectHandle[0] id
-                object_handle_count += 1;
-            }
-            if Self::object_handle_of_association_contains(cmd, 0x00000006) {
-                Self::write_u32(buffer, &mut offset, 0x00000007); // ObjectHandle[0] id
-                object_handle_count += 1;
-            }
         }
         Self::write_u32(buffer, &mut object_handle_offset, object_handle_count); // NumObjectHandles
         let total_len = offset as u32;
@@ -442,7 +1022,7 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
                 Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
                 Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
                 Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
-                Self::write_u32(buffer, &mut offset, 0x8000+0x2000+16); // Object Compressed Size
+                Self::write_u32(buffer, &mut offset, self.nes_rom_size.unwrap_or_else(|| self.nes_rom_size_from_config())); // Object Compressed Size
                 Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
                 Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
                 Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
@@ -506,7 +1086,9 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
                 Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
                 Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
                 Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
-                Self::write_u32(buffer, &mut offset, (0x10000 - 0x8000) * 32); // Object Compressed Size
+                // The SNES ROM size comes from the cartridge header, the configuration says
+                // nothing about it: 1 MB of LoROM until a dump has run
+                Self::write_u32(buffer, &mut offset, self.snes_rom_size.unwrap_or((0x10000 - 0x8000) * 32)); // Object Compressed Size
                 Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
                 Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
                 Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
@@ -566,121 +1148,1284 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
                 Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
                 Self::write_string(buffer, &mut offset, "0"); // Keywords
             }
-            _ => {
-                return 0;
-            }
-        }
-        let total_len = offset as u32;
-        Self::write_u32(buffer, &mut 0, total_len);
-        Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
-        Self::write_u16(buffer, &mut 6, 0x1008);    // Operation: GetStorageIDs
-        Self::write_u32(buffer, &mut 8, transaction_id);
-
-        offset
-    }
-
-    async fn generate_rom_object_response(&mut self, transaction_id: u32, buffer: &mut [u8], console: MsgStartConsole) -> usize {
-        let mut offset = 0;
-        self.out_channel.send(Msg::Start{console}).await;
-        let receiver = self.in_channel.receiver();
-        loop {
-            match receiver.receive().await {
-                Msg::DumpSetupData {rom_size} => {
-                    Self::write_u32(buffer, &mut offset, rom_size + 12);
-                    Self::write_u16(buffer, &mut offset, 2);         // ContainerType: Data
-                    Self::write_u16(buffer, &mut offset, 0x1009);    // Operation: GetObject
-                    Self::write_u32(buffer, &mut offset, transaction_id);
-                },
-                Msg::Data {data, length} => {
-                    let buffer_write_size = core::cmp::min(length, self.max_packet_size() - 1 - offset);
-                    Self::write_buffer(buffer, &mut offset, &data[..buffer_write_size]);
-                    if offset == self.max_packet_size() - 1 {
-                        offset = 0;
-                        match self.write_packet(&buffer[..self.max_packet_size() - 1]).await {
-                            Ok(_) => {
-                                if buffer_write_size != length {
-                                    Self::write_buffer(buffer, &mut offset, &data[buffer_write_size..]);
-                                }
-                            }
-                            _ => {
-                                // Allow the USB stack some breathing room; not strictly required
-                                // but avoids busy‑looping if the host stalls communication.
-                                Timer::after_millis(1).await;
-                                break;
-                            }
-                        }
-                    }
-                },
-                Msg::End => {
-                    if offset > 0 {
-                        match self.write_packet(&buffer[..offset]).await {
-                            Ok(_) => {},
-                            _ => {
-                                // Allow the USB stack some breathing room; not strictly required
-                                // but avoids busy‑looping if the host stalls communication.
-                                Timer::after_millis(1).await;
-                            }
-                        }
-                    }
-                    if offset % 64 == 0 {
-                        match self.write_packet(&[]).await {
-                            Ok(_) => {},
-                            _ => {
-                                // Allow the USB stack some breathing room; not strictly required
-                                // but avoids busy‑looping if the host stalls communication.
-                                Timer::after_millis(1).await;
-                            }
-                        }
-                    }
-                    break;
-                },
-                _ => {}
-            }
-        }
-
-        0
-    }
-
-    fn generate_config_json_object_response(&mut self, transaction_id: u32, buffer: &mut [u8]) -> usize {
-        let mut offset = 12;
-        Self::write_buffer(buffer, &mut offset, &self.configuration_file[0..self.configuration_file_size]); // File content
-
-        let total_len = offset as u32;
-        Self::write_u32(buffer, &mut 0, total_len);
-        Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
-        Self::write_u16(buffer, &mut 6, 0x1009);    // Operation: GetStorageIDs
-        Self::write_u32(buffer, &mut 8, transaction_id);
-
-        offset
-    }
-
-    async fn generate_object_response<'a>(&mut self, transaction_id: u32, buffer: &mut [u8], cmd: &PtpCommand<'a>) -> usize {
-        let object_handle= u32::from_le_bytes(cmd.payload[0..4].try_into().unwrap());
-        match object_handle {
-            0x00000002 => {
-                self.generate_rom_object_response(transaction_id, buffer, MsgStartConsole::Nes).await
-            }
-            0x00000003 => {
-                self.generate_config_json_object_response(transaction_id, buffer)
-            }
-            0x00000005 => {
-                self.generate_rom_object_response(transaction_id, buffer, MsgStartConsole::Snes).await
+            0x00000008 => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, self.diagnostics().len() as u32); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000000); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "diagnostics.txt"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
             }
-            0x00000007 => {
-                self.generate_rom_object_response(transaction_id, buffer, MsgStartConsole::Sms).await
+            0x0000000B => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, self.lookup_result().len() as u32); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "lookup.txt"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
             }
-            _ => {
-                0
+            ROMDB_HANDLE | PATCHES_HANDLE => {
+                let Some(upload) = self.uploads.iter().find(|upload| upload.handle == object_handle) else {
+                    return 0;
+                };
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0000); // Protection Status
+                Self::write_u32(buffer, &mut offset, upload.size as u32); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000000); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, upload.name); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
             }
-        }
-    }
+            SRAM_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0000); // Protection Status
+                Self::write_u32(buffer, &mut offset, SRAM_SIZE as u32); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "sram.bin"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x00000018 => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3001); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3001); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000000); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0x0001); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "Game Boy"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x00000019 => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x4000 * 512); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000018); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "rom.gb"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x0000001C => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x2000 * 16); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000018); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "save.sav"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            PRG_RAM_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x2000); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "prg-ram.bin"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x0000001A => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3001); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3001); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000000); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0x0001); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "PC Engine"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x0000001B => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x100000); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x0000001A); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "rom.pce"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            VRC7_PATCHES_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x80); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "vrc7-patches.bin"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            handle if self.quattro_game_handles().is_some_and(|games| games.contains(&handle)) => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, QUATTRO_GAME_KB as u32 * 1024 + 16); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, QUATTRO_GAME_NAMES[(handle - QUATTRO_GAME_HANDLE) as usize]); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            MULTI_DUMP_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0xFFFFFFFF); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000000); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "multi-dump.bin"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            NAMCO163_RAM_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x80); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "namco163-ram.bin"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            DUMP_LOG_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, self.dump_log.fill as u32); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000000); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "dump.log"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x0000001D => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3001); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3001); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000000); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0x0001); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "Nintendo 64"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x0000001E => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x2000000); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x0000001D); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "rom.z64"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x0000001F => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3001); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3001); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000000); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0x0001); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "Game Boy Advance"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x00000020 => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x2000); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x0000001F); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "eeprom.sav"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            BANDAI_EEPROM_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0xFFFFFFFF); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "eeprom.bin"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x00000021 => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3001); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3001); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000000); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0x0001); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "Genesis"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x00000022 => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x400000); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000021); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "rom.md"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            NES_RAW_PRG_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, self.dumper_config.prg as u32 * 1024); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "rom.prg"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            NES2_ROM_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, (self.dumper_config.prg as u32 + self.dumper_config.chr as u32) * 1024 + 16); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "rom.nes2"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            GBA_FLASH_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0xFFFFFFFF); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x0000001F); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "flash.sav"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x00000025 => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3001); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3001); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000000); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0x0001); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "Atari 2600"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            0x00000026 => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0xFFFFFFFF); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000025); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "rom.a26"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            WRAM_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x2000); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "wram.bin"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            CHECKSUMS_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, self.checksums_json().len() as u32); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "checksums.json"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            SNES_PARTIAL_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x8000); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000004); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "partial.sfc"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            EXRAM_HANDLE => {
+                Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID
+                Self::write_u16(buffer, &mut offset, 0x3000); // Object Format
+                Self::write_u16(buffer, &mut offset, 0x0001); // Protection Status
+                Self::write_u32(buffer, &mut offset, 0x400); // Object Compressed Size
+                Self::write_u16(buffer, &mut offset, 0x3000); // Thumb Format
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Compressed Size
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Thumb Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Width
+                Self::write_u32(buffer, &mut offset, 0); // Image Pix Height
+                Self::write_u32(buffer, &mut offset, 0); // Image Bit Depth
+                Self::write_u32(buffer, &mut offset, 0x00000001); // Parent Object
+                Self::write_u16(buffer, &mut offset, 0); // Association Type
+                Self::write_u32(buffer, &mut offset, 0); // Association Description
+                Self::write_u32(buffer, &mut offset, 0); // Sequence Number
+                Self::write_string(buffer, &mut offset, "exram.bin"); // Filename
+                Self::write_string(buffer, &mut offset, "20251205T173222.0Z"); // Date Created
+                Self::write_string(buffer, &mut offset, "20251205T183222.0Z"); // Date Modified
+                Self::write_string(buffer, &mut offset, "0"); // Keywords
+            }
+            _ => {
+                return 0;
+            }
+        }
+        let total_len = offset as u32;
+        Self::write_u32(buffer, &mut 0, total_len);
+        Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
+        Self::write_u16(buffer, &mut 6, 0x1008);    // Operation: GetStorageIDs
+        Self::write_u32(buffer, &mut 8, transaction_id);
+
+        offset
+    }
+
+    /// Appends `data` to the packet being built in `buffer`, sending it every time it fills up.
+    /// Packets carry `max_packet_size - 1` bytes, so a dump never needs a ZLP mid-stream.
+    async fn push_stream(&mut self, buffer: &mut [u8], offset: &mut usize, data: &[u8]) -> Result<(), EndpointError> {
+        let mut data = data;
+        while !data.is_empty() {
+            let buffer_write_size = core::cmp::min(data.len(), self.max_packet_size() - 1 - *offset);
+            Self::write_buffer(buffer, offset, &data[..buffer_write_size]);
+            data = &data[buffer_write_size..];
+            if *offset == self.max_packet_size() - 1 {
+                *offset = 0;
+                self.write_packet(&buffer[..self.max_packet_size() - 1]).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Streams the dumper messages into `buffer` until the dumper announces a size or ends the
+    /// dump, keeping track of the side results (verification, checksums, errors) on the way.
+    async fn next_dump_event(&mut self, buffer: &mut [u8], offset: &mut usize) -> DumpEvent {
+        let receiver = self.in_channel.receiver();
+        loop {
+            let msg = receiver.receive().await;
+            crate::USB_ACTIVITY.store(true, Ordering::Relaxed);
+            match msg {
+                Msg::DumpSetupData {rom_size} => {
+                    // A new dump, the checks of the previous one no longer apply
+                    if self.dump_progress.total == 0 {
+                        self.crc_check = None;
+                        self.verify_result = None;
+                    }
+                    // The combined dump announces its second ROM halfway through
+                    self.dump_progress.total += rom_size;
+                    match self.dump_progress.handle {
+                        0x00000002 => self.nes_rom_size = Some(rom_size),
+                        0x00000005 => self.snes_rom_size = Some(rom_size),
+                        _ => {}
+                    }
+                    return DumpEvent::Setup(rom_size);
+                },
+                Msg::Data {data, length} => {
+                    self.dump_log.push_chunk(length);
+                    if self.dump_progress.advance(length) {
+                        let transaction_id = self.dump_progress.transaction_id;
+                        self.send_event(EVENT_DEVICE_PROP_CHANGED, transaction_id, DEVICE_PROP_DUMP_PROGRESS as u32).await;
+                    }
+                    if self.push_stream(buffer, offset, &data[..length]).await.is_err() {
+                        // Allow the USB stack some breathing room; not strictly required
+                        // but avoids busy‑looping if the host stalls communication.
+                        Timer::after_millis(1).await;
+                        return DumpEvent::Stalled;
+                    }
+                },
+                Msg::VerifyResult {bytes_checked, mismatch_count} => {
+                    self.verify_result = Some((bytes_checked, mismatch_count));
+                },
+                Msg::Checksum {prg_crc32, chr_crc32} => {
+                    self.prg_crc32 = Some(prg_crc32);
+                    self.chr_crc32 = Some(chr_crc32);
+                },
+                Msg::CrcCheck {passed, expected_crc, actual_crc} => {
+                    self.crc_check = Some((passed, expected_crc, actual_crc));
+                },
+                Msg::CartridgeInfo {info} => {
+                    self.cartridge_info = info;
+                },
+                Msg::Error {code, context} => {
+                    self.last_error = Some((code, context));
+                },
+                Msg::Warning {code} => {
+                    self.last_warning = Some(code);
+                },
+                Msg::End => {
+                    return DumpEvent::End;
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// Sends what is left in `buffer` and closes the data phase.
+    async fn finish_stream(&mut self, buffer: &mut [u8], offset: usize) {
+        if offset > 0 {
+            match self.write_packet(&buffer[..offset]).await {
+                Ok(_) => {},
+                _ => {
+                    // Allow the USB stack some breathing room; not strictly required
+                    // but avoids busy‑looping if the host stalls communication.
+                    Timer::after_millis(1).await;
+                }
+            }
+        }
+        // Data is sent max_packet_size - 1 bytes at a time, so the last packet is
+        // already a short one
+        if self.needs_zlp(offset) {
+            self.write_zlp().await;
+        }
+    }
+
+    /// Checks the size announced by the dumper, recording an error and falling back to an
+    /// unknown size when it is beyond any real cartridge.
+    fn checked_transfer_mode(&mut self, rom_size: u32) -> MtpTransferMode {
+        if rom_size > MAX_ROM_SIZE {
+            self.last_error = Some((Msg::ERROR_ROM_SIZE, rom_size));
+            return MtpTransferMode::ChunkedUnknown;
+        }
+        match rom_size {
+            0 => MtpTransferMode::ChunkedUnknown,
+            size => MtpTransferMode::KnownSize(size),
+        }
+    }
+
+    async fn generate_rom_object_response(&mut self, transaction_id: u32, buffer: &mut [u8], start: Msg) -> usize {
+        let mut offset = 0;
+        self.busy.store(true, Ordering::Relaxed);
+        self.last_error = None;
+        self.last_warning = None;
+        self.dump_log.clear();
+        self.out_channel.send(start).await;
+        loop {
+            match self.next_dump_event(buffer, &mut offset).await {
+                DumpEvent::Setup(rom_size) => {
+                    let mode = self.checked_transfer_mode(rom_size);
+                    Self::write_u32(buffer, &mut offset, mode.container_length());
+                    Self::write_u16(buffer, &mut offset, 2);         // ContainerType: Data
+                    Self::write_u16(buffer, &mut offset, 0x1009);    // Operation: GetObject
+                    Self::write_u32(buffer, &mut offset, transaction_id);
+                },
+                DumpEvent::End => {
+                    self.finish_stream(buffer, offset).await;
+                    break;
+                },
+                DumpEvent::Stalled => {
+                    break;
+                },
+            }
+        }
+        self.busy.store(false, Ordering::Relaxed);
+
+        0
+    }
+
+    /// Dumps the NES and then the SNES slot of a dual adapter in a single data phase, behind a
+    /// [`CombinedDumpFormat`] manifest. The SNES size is only known once the NES ROM has been
+    /// streamed, so the transfer has no announced length and the SNES ROM runs to its end.
+    async fn generate_multi_dump_response(&mut self, transaction_id: u32, buffer: &mut [u8]) -> usize {
+        let mut offset = 0;
+        self.busy.store(true, Ordering::Relaxed);
+        self.last_error = None;
+        self.last_warning = None;
+        self.dump_log.clear();
+        self.out_channel.send(Msg::Start{console: MsgStartConsole::Nes}).await;
+        let mut snes_started = false;
+        loop {
+            match self.next_dump_event(buffer, &mut offset).await {
+                DumpEvent::Setup(rom_size) if !snes_started => {
+                    let nes_size = match self.checked_transfer_mode(rom_size) {
+                        MtpTransferMode::KnownSize(size) => size,
+                        MtpTransferMode::ChunkedUnknown => 0,
+                    };
+                    Self::write_u32(buffer, &mut offset, MtpTransferMode::ChunkedUnknown.container_length());
+                    Self::write_u16(buffer, &mut offset, 2);         // ContainerType: Data
+                    Self::write_u16(buffer, &mut offset, 0x1009);    // Operation: GetObject
+                    Self::write_u32(buffer, &mut offset, transaction_id);
+                    let manifest = CombinedDumpFormat::new(nes_size).to_bytes();
+                    if self.push_stream(buffer, &mut offset, &manifest).await.is_err() {
+                        break;
+                    }
+                },
+                DumpEvent::Setup(_) => {},
+                DumpEvent::End if !snes_started => {
+                    snes_started = true;
+                    self.out_channel.send(Msg::Start{console: MsgStartConsole::Snes}).await;
+                },
+                DumpEvent::End => {
+                    self.finish_stream(buffer, offset).await;
+                    break;
+                },
+                DumpEvent::Stalled => {
+                    break;
+                },
+            }
+        }
+        self.busy.store(false, Ordering::Relaxed);
+
+        0
+    }
+
+    fn generate_config_json_object_response(&mut self, transaction_id: u32, buffer: &mut [u8]) -> usize {
+        let mut offset = 12;
+        Self::write_buffer(buffer, &mut offset, &self.configuration_file[0..self.configuration_file_size]); // File content
+
+        let total_len = offset as u32;
+        Self::write_u32(buffer, &mut 0, total_len);
+        Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
+        Self::write_u16(buffer, &mut 6, 0x1009);    // Operation: GetStorageIDs
+        Self::write_u32(buffer, &mut 8, transaction_id);
+
+        offset
+    }
+
+    fn diagnostics(&self) -> String<512> {
+        let mut text = String::new();
+        match self.verify_result {
+            Some((bytes_checked, mismatch_count)) => {
+                let _ = writeln!(text, "snes_verify: checked={} mismatches={}", bytes_checked, mismatch_count);
+            }
+            None => {
+                let _ = writeln!(text, "snes_verify: not run");
+            }
+        }
+        match self.crc_check {
+            Some((true, _, _)) => {
+                let _ = writeln!(text, "nes_crc_check: passed");
+            }
+            Some((false, expected, actual)) => {
+                let _ = writeln!(text, "nes_crc_check: failed prg=0x{:08X}/0x{:08X} chr=0x{:08X}/0x{:08X}",
+                    actual.0, expected.0, actual.1, expected.1);
+            }
+            None => {}
+        }
+        if let Some((code, context)) = self.last_error {
+            let _ = writeln!(text, "last_error: 0x{:02X} (0x{:08X})", code, context);
+        }
+        if let Some(code) = self.last_warning {
+            let _ = writeln!(text, "last_warning: 0x{:02X}", code);
+        }
+        match self.cartridge_info.best_guess_mapper {
+            Some(mapper) => {
+                let _ = writeln!(text, "nes_mapper_guess: {}", mapper);
+            }
+            None => {
+                let _ = writeln!(text, "nes_mapper_guess: unknown");
+            }
+        }
+        match self.cartridge_info.standard_pinout {
+            Some(true) => {
+                let _ = writeln!(text, "nes_pinout: standard");
+            }
+            Some(false) => {
+                let _ = writeln!(text, "nes_pinout: mismatch, PRG-ROM ignores /ROMSEL");
+            }
+            None => {}
+        }
+        match self.cartridge_info.slot_type {
+            Some(SlotType::Famicom60) => {
+                let _ = writeln!(text, "nes_slot: famicom60");
+            }
+            Some(SlotType::Nes72) => {
+                let _ = writeln!(text, "nes_slot: nes72");
+            }
+            None => {}
+        }
+        if let Some(read_latency_ns) = self.cartridge_info.read_latency_ns {
+            let _ = writeln!(text, "nes_read_latency_ns: {}", read_latency_ns);
+        }
+        if let Some(sram_write_protected) = self.cartridge_info.sram_write_protected {
+            let _ = writeln!(text, "nes_sram_write_protected: {}", sram_write_protected);
+        }
+        match self.cartridge_info.snes_storage {
+            Some(CartridgeStorage::MaskRom) => {
+                let _ = writeln!(text, "snes_storage: mask_rom");
+            }
+            Some(CartridgeStorage::Flash) => {
+                let _ = writeln!(text, "snes_storage: flash");
+            }
+            None => {}
+        }
+        if let Some(manufacturer) = self.cartridge_info.flash_manufacturer {
+            let _ = writeln!(text, "snes_flash_manufacturer: 0x{:02X}", manufacturer);
+        }
+        if let Some(detected_chr_kb) = self.cartridge_info.detected_chr_kb {
+            let _ = writeln!(text, "nes_detected_chr_kb: {}", detected_chr_kb);
+        }
+        match self.cartridge_info.address_bus {
+            Some(BusTestResult { swapped_bits: 0 }) => {
+                let _ = writeln!(text, "nes_address_bus: ok");
+            }
+            Some(BusTestResult { swapped_bits }) => {
+                let _ = writeln!(text, "nes_address_bus: suspect lines=0x{:04X}", swapped_bits);
+            }
+            None => {}
+        }
+        text
+    }
 
-    fn generate_delete_object_response<'a>(&mut self, cmd: &PtpCommand<'a>) -> usize {
-        let object_id= u32::from_le_bytes(cmd.payload[0..4].try_into().unwrap());
-        if object_id == 0x00000003 || object_id == 0xFFFFFFFF {
-            self.configuration_file_deleted = true;
+    fn lookup_result(&self) -> String<128> {
+        let mut text = String::new();
+        match self.prg_crc32.and_then(rom_database::lookup) {
+            Some((name, mapper)) => {
+                let _ = writeln!(text, "{}", name);
+                let _ = writeln!(text, "mapper: {}", mapper);
+                let _ = writeln!(text, "status: verified");
+            }
+            None => {
+                let _ = writeln!(text, "Unknown CRC");
+            }
         }
-        0
+        text
+    }
+
+    /// Size of rom.nes as [`crate::dumper::DumperClass`] would announce it for the current
+    /// configuration: header, trainer, PRG-ROM and CHR-ROM as the output format includes them.
+    fn nes_rom_size_from_config(&self) -> u32 {
+        let config = &self.dumper_config;
+        let has_header = matches!(config.output_format, NesOutputFormat::INes1 | NesOutputFormat::INes2);
+        let with_chr = config.output_format != NesOutputFormat::RawPrg;
+        let mut size = config.prg as u32 * 1024;
+        if with_chr {
+            size += config.chr as u32 * 1024;
+        }
+        if has_header {
+            size += 16;
+            if config.trainer_addr.is_some() {
+                size += NES_TRAINER_SIZE as u32;
+            }
+        }
+        size
+    }
+
+    /// Writes `value` as 8 uppercase hex digits, the way CRCs are listed in NES Cart DB.
+    fn format_u32_hex(value: u32) -> String<8> {
+        let mut text = String::new();
+        let _ = write!(text, "{:08X}", value);
+        text
+    }
+
+    /// CRCs of the last dump, 0 until a dump reported them.
+    fn checksums_json(&self) -> String<64> {
+        let mut text = String::new();
+        let _ = write!(text, "{{\"prg_crc32\": \"{}\", \"chr_crc32\": \"{}\"}}",
+            Self::format_u32_hex(self.prg_crc32.unwrap_or(0)),
+            Self::format_u32_hex(self.chr_crc32.unwrap_or(0)));
+        text
+    }
+
+    fn generate_checksums_object_response(&mut self, transaction_id: u32, buffer: &mut [u8]) -> usize {
+        let mut offset = 12;
+        Self::write_buffer(buffer, &mut offset, self.checksums_json().as_bytes()); // File content
+
+        let total_len = offset as u32;
+        Self::write_u32(buffer, &mut 0, total_len);
+        Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
+        Self::write_u16(buffer, &mut 6, 0x1009);    // Operation: GetObject
+        Self::write_u32(buffer, &mut 8, transaction_id);
+
+        offset
+    }
+
+    fn generate_lookup_object_response(&mut self, transaction_id: u32, buffer: &mut [u8]) -> usize {
+        let mut offset = 12;
+        Self::write_buffer(buffer, &mut offset, self.lookup_result().as_bytes()); // File content
+
+        let total_len = offset as u32;
+        Self::write_u32(buffer, &mut 0, total_len);
+        Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
+        Self::write_u16(buffer, &mut 6, 0x1009);    // Operation: GetObject
+        Self::write_u32(buffer, &mut 8, transaction_id);
+
+        offset
+    }
+
+    /// Writes the current value of device property `property`, or its factory default when
+    /// `factory_default` is set. Returns `false` for a property the device does not have.
+    fn write_device_prop_value(&self, buffer: &mut [u8], offset: &mut usize, property: u16, factory_default: bool) -> bool {
+        match property {
+            DEVICE_PROP_SYNCHRONIZATION_PARTNER => Self::write_string(buffer, offset, ""),
+            DEVICE_PROP_FRIENDLY_NAME => Self::write_string(buffer, offset, self.identity.model),
+            DEVICE_PROP_FUNCTIONAL_MODE => Self::write_u16(buffer, offset, 0), // Standard mode
+            // There is no real time clock to report
+            DEVICE_PROP_DATE_TIME => Self::write_string(buffer, offset, ""),
+            DEVICE_PROP_DUMP_PROGRESS => {
+                Self::write_u8(buffer, offset, if factory_default { 0 } else { self.dump_progress.percent });
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn generate_device_prop_desc_response<'a>(&self, transaction_id: u32, buffer: &mut [u8], cmd: &PtpCommand<'a>) -> usize {
+        let mut offset = 12;
+        let property = Self::command_parameter(cmd, 0) as u16;
+        let data_type = match property {
+            DEVICE_PROP_FUNCTIONAL_MODE => 0x0004,  // UINT16
+            DEVICE_PROP_DUMP_PROGRESS => 0x0002,    // UINT8
+            _ => 0xFFFF,                            // STR
+        };
+        Self::write_u16(buffer, &mut offset, property); // DevicePropertyCode
+        Self::write_u16(buffer, &mut offset, data_type); // DataType
+        Self::write_u8(buffer, &mut offset, 0x00); // GetSet: read only
+        if !self.write_device_prop_value(buffer, &mut offset, property, true) { // FactoryDefaultValue
+            return 0;
+        }
+        self.write_device_prop_value(buffer, &mut offset, property, false); // CurrentValue
+        if property == DEVICE_PROP_DUMP_PROGRESS {
+            Self::write_u8(buffer, &mut offset, 0x01); // FormFlag: Range
+            Self::write_u8(buffer, &mut offset, 0); // MinimumValue
+            Self::write_u8(buffer, &mut offset, 100); // MaximumValue
+            Self::write_u8(buffer, &mut offset, 1); // StepSize
+        } else {
+            Self::write_u8(buffer, &mut offset, 0x00); // FormFlag: None
+        }
+
+        let total_len = offset as u32;
+        Self::write_u32(buffer, &mut 0, total_len);
+        Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
+        Self::write_u16(buffer, &mut 6, 0x1014);    // Operation: GetDevicePropDesc
+        Self::write_u32(buffer, &mut 8, transaction_id);
+
+        offset
+    }
+
+    fn generate_device_prop_value_response<'a>(&self, transaction_id: u32, buffer: &mut [u8], cmd: &PtpCommand<'a>) -> usize {
+        let mut offset = 12;
+        if !self.write_device_prop_value(buffer, &mut offset, Self::command_parameter(cmd, 0) as u16, false) {
+            return 0;
+        }
+
+        let total_len = offset as u32;
+        Self::write_u32(buffer, &mut 0, total_len);
+        Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
+        Self::write_u16(buffer, &mut 6, 0x1015);    // Operation: GetDevicePropValue
+        Self::write_u32(buffer, &mut 8, transaction_id);
+
+        offset
+    }
+
+    fn generate_dump_log_object_response(&mut self, transaction_id: u32, buffer: &mut [u8]) -> usize {
+        let mut offset = 12;
+        Self::write_buffer(buffer, &mut offset, self.dump_log.as_bytes()); // File content
+
+        let total_len = offset as u32;
+        Self::write_u32(buffer, &mut 0, total_len);
+        Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
+        Self::write_u16(buffer, &mut 6, 0x1009);    // Operation: GetObject
+        Self::write_u32(buffer, &mut 8, transaction_id);
+
+        offset
+    }
+
+    fn generate_diagnostics_object_response(&mut self, transaction_id: u32, buffer: &mut [u8]) -> usize {
+        let mut offset = 12;
+        Self::write_buffer(buffer, &mut offset, self.diagnostics().as_bytes()); // File content
+
+        let total_len = offset as u32;
+        Self::write_u32(buffer, &mut 0, total_len);
+        Self::write_u16(buffer, &mut 4, 2);         // ContainerType: Data
+        Self::write_u16(buffer, &mut 6, 0x1009);    // Operation: GetObject
+        Self::write_u32(buffer, &mut 8, transaction_id);
+
+        offset
+    }
+
+    async fn generate_object_response<'a>(&mut self, transaction_id: u32, buffer: &mut [u8], cmd: &PtpCommand<'a>) -> usize {
+        let object_handle= u32::from_le_bytes(cmd.payload[0..4].try_into().unwrap());
+        self.dump_progress.start(object_handle, transaction_id);
+        match object_handle {
+            0x00000002 => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::Start{console: MsgStartConsole::Nes}).await
+            }
+            0x00000003 => {
+                self.generate_config_json_object_response(transaction_id, buffer)
+            }
+            0x00000005 => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::Start{console: MsgStartConsole::Snes}).await
+            }
+            0x00000007 => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::Start{console: MsgStartConsole::Sms}).await
+            }
+            0x00000008 => {
+                self.generate_diagnostics_object_response(transaction_id, buffer)
+            }
+            DUMP_LOG_HANDLE => {
+                self.generate_dump_log_object_response(transaction_id, buffer)
+            }
+            0x0000000B => {
+                self.generate_lookup_object_response(transaction_id, buffer)
+            }
+            CHECKSUMS_HANDLE => {
+                self.generate_checksums_object_response(transaction_id, buffer)
+            }
+            SRAM_HANDLE => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartMemory{memory: MsgStartMemory::NesSram}).await
+            }
+            PRG_RAM_HANDLE => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartMemory{memory: MsgStartMemory::NesPrgRam}).await
+            }
+            VRC7_PATCHES_HANDLE => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartMemory{memory: MsgStartMemory::Vrc7Patches}).await
+            }
+            NAMCO163_RAM_HANDLE => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartMemory{memory: MsgStartMemory::Namco163Ram}).await
+            }
+            BANDAI_EEPROM_HANDLE => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartMemory{memory: MsgStartMemory::BandaiEeprom}).await
+            }
+            WRAM_HANDLE => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartMemory{memory: MsgStartMemory::Mmc3Wram}).await
+            }
+            EXRAM_HANDLE => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartMemory{memory: MsgStartMemory::Mmc5ExRam}).await
+            }
+            GBA_FLASH_HANDLE => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartMemory{memory: MsgStartMemory::GbaFlash}).await
+            }
+            SNES_PARTIAL_HANDLE => {
+                let start = Msg::StartPartialSnes { from_bank: 0, to_bank: 1, rom_type: SnesRomType::LO as u8 };
+                self.generate_rom_object_response(transaction_id, buffer, start).await
+            }
+            NES_RAW_PRG_HANDLE => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartNes{format: NesOutputFormat::RawPrg}).await
+            }
+            NES2_ROM_HANDLE => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartNes{format: NesOutputFormat::INes2}).await
+            }
+            handle if self.quattro_game_handles().is_some_and(|games| games.contains(&handle)) => {
+                let game = (handle - QUATTRO_GAME_HANDLE) as u16;
+                let start = Msg::StartPartial {
+                    console: MsgStartConsole::Nes,
+                    offset_kb: game * QUATTRO_GAME_KB,
+                    length_kb: QUATTRO_GAME_KB,
+                };
+                self.generate_rom_object_response(transaction_id, buffer, start).await
+            }
+            MULTI_DUMP_HANDLE => {
+                self.generate_multi_dump_response(transaction_id, buffer).await
+            }
+            0x00000019 => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::Start{console: MsgStartConsole::Gb}).await
+            }
+            0x0000001B => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::Start{console: MsgStartConsole::Pce}).await
+            }
+            0x0000001C => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartMemory{memory: MsgStartMemory::GbSram}).await
+            }
+            0x0000001E => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::Start{console: MsgStartConsole::N64}).await
+            }
+            0x00000022 => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::Start{console: MsgStartConsole::Genesis}).await
+            }
+            0x00000026 => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::Start{console: MsgStartConsole::Atari2600}).await
+            }
+            0x00000020 => {
+                self.generate_rom_object_response(transaction_id, buffer, Msg::StartMemory{memory: MsgStartMemory::GbaEeprom}).await
+            }
+            _ => {
+                0
+            }
+        }
+    }
+
+    /// DeleteObject: config.json is the only object that can be deleted, the others are made by
+    /// the dumper. Returns the handle of the deleted object.
+    fn delete_object<'a>(&mut self, cmd: &PtpCommand<'a>) -> Result<u32, MtpCommandError> {
+        let object_id = Self::command_parameter(cmd, 0);
+        match object_id {
+            0x00000003 | 0xFFFFFFFF if !self.configuration_file_deleted => {
+                self.configuration_file_deleted = true;
+                Ok(0x00000003)
+            }
+            0xFFFFFFFF => Err(MtpCommandError::ObjectWriteProtected),
+            _ if OBJECT_REGISTRY.iter().any(|object| object.handle == object_id) && self.object_present(object_id) => {
+                Err(MtpCommandError::ObjectWriteProtected)
+            }
+            _ => Err(MtpCommandError::InvalidObjectHandle),
+        }
+    }
+
+    /// BeginEditObject (Android extension): only config.json can be edited.
+    fn begin_edit_object<'a>(&mut self, cmd: &PtpCommand<'a>) -> MtpCommandError {
+        if Self::command_parameter(cmd, 0) != 0x00000003 {
+            return MtpCommandError::InvalidObjectHandle;
+        }
+        self.edit_lock = true;
+        MtpCommandError::Ok
+    }
+
+    /// TruncateObject (Android extension): cuts config.json to the 64 bit length given.
+    fn truncate_object<'a>(&mut self, cmd: &PtpCommand<'a>) -> MtpCommandError {
+        if !self.edit_lock || Self::command_parameter(cmd, 0) != 0x00000003 {
+            return MtpCommandError::InvalidObjectHandle;
+        }
+        let length = (Self::command_parameter(cmd, 2) as u64) << 32 | Self::command_parameter(cmd, 1) as u64;
+        self.configuration_file_size = core::cmp::min(length, self.configuration_file_size as u64) as usize;
+        self.configuration_file[self.configuration_file_size..].fill(0);
+        MtpCommandError::Ok
+    }
+
+    /// EndEditObject (Android extension): applies the edited config.json and stores it back in
+    /// the canonical form the dumper serializes.
+    async fn end_edit_object<'a>(&mut self, cmd: &PtpCommand<'a>) -> MtpCommandError {
+        if !self.edit_lock || Self::command_parameter(cmd, 0) != 0x00000003 {
+            return MtpCommandError::InvalidObjectHandle;
+        }
+        self.edit_lock = false;
+        match DumperConfig::from_json(&self.configuration_file[..self.configuration_file_size]) {
+            Some(config) => {
+                self.configuration_file.fill(0);
+                if let Ok(size) = serde_json_core::to_slice(&config, self.configuration_file) {
+                    self.configuration_file_size = size;
+                }
+                self.send_updated_dumper_config(&config).await;
+            }
+            None => {
+                // Keep running with the previous configuration
+                self.last_warning = Some(Msg::WARNING_CONFIG_REJECTED);
+            }
+        }
+        MtpCommandError::Ok
     }
 
     async fn generate_send_object_info_response<'a>(&mut self, buffer: &mut [u8], cmd: &PtpCommand<'a>) -> usize {
@@ -690,14 +2435,14 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
             return 0;
         }
 
-        // Read one USB bulk packet from the host.
-        let _ = self.read_packet(&mut buffer[0..64]).await;
-        let len = match self.read_packet(&mut buffer[64..128]).await {
-            Ok(n) if n > 0 => {
-                match self.parse_mtp_command(&buffer, MtpContainerType::Data) {
+        // The ObjectInfo dataset spans as many USB bulk packets as its strings need.
+        let received = self.read_container(buffer).await;
+        let len = match received {
+            n if n > 0 => {
+                match self.parse_mtp_command(&buffer[..received], MtpContainerType::Data) {
                     Ok(cmd) => {
-                        let command_result = match cmd.op_code {
-                            0x100c => {
+                        let command_result = match MtpOpCode::try_from(cmd.op_code) {
+                            Ok(MtpOpCode::SendObjectInfo) => {
                                 let object_format = u16::from_le_bytes(cmd.payload[4..6].try_into().unwrap());
                                 let object_compressed_size = u32::from_le_bytes(cmd.payload[8..12].try_into().unwrap());
                                 let parent_object=u32::from_le_bytes(cmd.payload[38..42].try_into().unwrap());
@@ -705,34 +2450,37 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
                                 let association_description=u32::from_le_bytes(cmd.payload[44..48].try_into().unwrap());
                                 let filename_length = cmd.payload[52] as usize -1;
                                 let filename = &cmd.payload[53..53+filename_length*2];
-                                if object_format != 0x3000 {
-                                    Err(MtpCommandError::InvalidObjectFormatCode)
-                                } else if object_compressed_size as usize > self.configuration_file.len()  {
-                                    Err(MtpCommandError::ObjectTooLarge)
-                                } else if parent_object != 0x00000001 {
-                                    Err(MtpCommandError::InvalidParentObject)
-                                } else if association_type != 0 {
-                                    Err(MtpCommandError::OperationNotSupported)
-                                } else if association_description != 0 {
-                                    Err(MtpCommandError::OperationNotSupported)
-                                } else if filename_length != "config.json".len() ||
-                                    filename.chunks_exact(2)
-                                        .map(|chunk| u16::from_le_bytes(chunk.try_into().unwrap()))
-                                        .zip("config.json".encode_utf16().chain(iter::repeat(0))) // evitiamo panic se lunghezze diverse
-                                        .any(|(a, b)| a != b){
-                                    Err(MtpCommandError::OperationNotSupported)
+                                // (handle, capacity, expected parent)
+                                let target = if Self::filename_matches(filename, filename_length, "config.json") {
+                                    Some((0x00000003, self.configuration_file.len(), 0x00000001))
+                                } else if Self::filename_matches(filename, filename_length, "sram.bin") {
+                                    Some((SRAM_HANDLE, SRAM_SIZE, 0x00000001))
                                 } else {
-                                    Ok(())
+                                    self.uploads.iter()
+                                        .find(|upload| Self::filename_matches(filename, filename_length, upload.name))
+                                        .map(|upload| (upload.handle, upload.data.len(), 0x00000000))
+                                };
+                                match target {
+                                    _ if object_format != 0x3000 => Err(MtpCommandError::InvalidObjectFormatCode),
+                                    None => Err(MtpCommandError::OperationNotSupported),
+                                    Some((_, capacity, _)) if object_compressed_size as usize > capacity => Err(MtpCommandError::ObjectTooLarge),
+                                    Some((_, _, parent)) if parent_object != parent && !(parent == 0 && parent_object == 0xFFFFFFFF) => Err(MtpCommandError::InvalidParentObject),
+                                    _ if association_type != 0 => Err(MtpCommandError::OperationNotSupported),
+                                    _ if association_description != 0 => Err(MtpCommandError::OperationNotSupported),
+                                    Some((handle, _, parent)) => {
+                                        self.pending_upload = handle;
+                                        Ok((handle, parent))
+                                    }
                                 }
                             }
                             _ => {Err(MtpCommandError::OperationNotSupported)},
                         };
                         match command_result {
-                            Ok(()) => {
+                            Ok((handle, parent)) => {
                                 let mut offset = self.generate_ok_response_block(cmd.transaction_id, buffer);
                                 Self::write_u32(buffer, &mut offset, 0x00010001); // StorageID in which the object will be stored
-                                Self::write_u32(buffer, &mut offset, 0x00000001);// Parent ObjectHandle in which the object will be stored
-                                Self::write_u32(buffer, &mut offset, 0x00000003); // Reserved ObjectHandle for the incoming object
+                                Self::write_u32(buffer, &mut offset, parent);// Parent ObjectHandle in which the object will be stored
+                                Self::write_u32(buffer, &mut offset, handle); // Reserved ObjectHandle for the incoming object
                                 let length = offset.to_le_bytes();
                                 buffer[0..4].copy_from_slice(&length);
                                 offset
@@ -752,38 +2500,197 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
                 0
             }
         };
-        let mut offset = 0;
-        while offset < len {
-            let end = core::cmp::min(offset + self.max_packet_size(), len);
-            let chunk = &buffer[offset..end];
-            match self.write_packet(&chunk).await {
-                _ => {
-                    // Allow the USB stack some breathing room; not strictly required
-                    // but avoids busy‑looping if the host stalls communication.
-                    Timer::after_millis(1).await;
+        if len > 0 {
+            self.write_response_buffer(buffer, len).await;
+        }
+        0
+    }
+
+    /// Reads packets until the container announced in the first one is complete or `buffer` is
+    /// full, and returns the number of bytes received.
+    async fn read_container(&mut self, buffer: &mut [u8]) -> usize {
+        let packet_size = self.max_packet_size();
+        let mut received = 0;
+        while received + packet_size <= buffer.len() {
+            match self.read_packet(&mut buffer[received..received + packet_size]).await {
+                Ok(n) => {
+                    received += n;
+                    let complete = received >= 4 &&
+                        received >= u32::from_le_bytes(buffer[0..4].try_into().unwrap()) as usize;
+                    if n < packet_size || complete {
+                        break;
+                    }
                 }
+                _ => break,
             }
-            offset = end;
         }
-        0
+        received
+    }
+
+    fn filename_matches(filename: &[u8], filename_length: usize, name: &str) -> bool {
+        filename_length == name.len() &&
+            filename.chunks_exact(2)
+                .map(|chunk| u16::from_le_bytes(chunk.try_into().unwrap()))
+                .zip(name.encode_utf16().chain(iter::repeat(0))) // evitiamo panic se lunghezze diverse
+                .all(|(a, b)| a == b)
+    }
+
+    /// Receives the data phase of a SendObject into the buffer of the pending upload.
+    async fn receive_uploaded_object(&mut self, buffer: &mut [u8]) {
+        let Some(index) = self.uploads.iter().position(|upload| upload.handle == self.pending_upload) else {
+            return;
+        };
+        let packet_size = self.max_packet_size();
+        let mut container_length = usize::MAX;
+        let mut received = 0;
+        self.uploads[index].size = 0;
+        while received < container_length {
+            let n = match self.read_packet(&mut buffer[..packet_size]).await {
+                Ok(n) => n,
+                _ => break,
+            };
+            let mut payload = &buffer[..n];
+            if received == 0 {
+                if n < 12 {
+                    break;
+                }
+                container_length = u32::from_le_bytes(buffer[0..4].try_into().unwrap()) as usize;
+                payload = &buffer[12..n];
+            }
+            received += n;
+            let upload = &mut self.uploads[index];
+            let length = payload.len().min(upload.data.len() - upload.size);
+            upload.data[upload.size..upload.size + length].copy_from_slice(&payload[..length]);
+            upload.size += length;
+            if n < packet_size {
+                break;
+            }
+        }
+        if self.pending_upload == PATCHES_HANDLE {
+            self.send_patches(index).await;
+        }
+    }
+
+    /// Parses the `[[address, value], ...]` array of an uploaded patches.json and forwards every
+    /// entry to the dumper.
+    async fn send_patches(&mut self, index: usize) {
+        let upload = &self.uploads[index];
+        let patches = match serde_json_core::from_slice::<heapless::Vec<(u16, u8), MAX_PATCHES>>(&upload.data[..upload.size]) {
+            Ok((patches, _)) => patches,
+            _ => return,
+        };
+        for (address, value) in patches {
+            self.out_channel.send(Msg::Patch { address, value }).await;
+        }
+    }
+
+    /// Forwards the data phase of a SendObject on sram.bin to the dumper, which writes it back
+    /// to the cartridge as it arrives.
+    async fn receive_sram(&mut self, buffer: &mut [u8]) {
+        let packet_size = self.max_packet_size();
+        let mut container_length = usize::MAX;
+        let mut received = 0;
+        let mut chunk = [0u8; Msg::DATA_CHANNEL_SIZE];
+        let mut chunk_length = 0;
+        let mut sram_offset = 0;
+        self.busy.store(true, Ordering::Relaxed);
+        while received < container_length {
+            let n = match self.read_packet(&mut buffer[..packet_size]).await {
+                Ok(n) => n,
+                _ => break,
+            };
+            let mut start = 0;
+            if received == 0 {
+                if n < 12 {
+                    break;
+                }
+                container_length = u32::from_le_bytes(buffer[0..4].try_into().unwrap()) as usize;
+                start = 12;
+            }
+            received += n;
+            for index in start..n {
+                if sram_offset + chunk_length >= SRAM_SIZE {
+                    break;
+                }
+                chunk[chunk_length] = buffer[index];
+                chunk_length += 1;
+                if chunk_length == chunk.len() {
+                    self.out_channel.send(Msg::WriteSram { data: chunk, length: chunk_length, offset: sram_offset as u16 }).await;
+                    sram_offset += chunk_length;
+                    chunk_length = 0;
+                }
+            }
+            if n < packet_size {
+                break;
+            }
+        }
+        // The last bytes of a sram.bin that is not a whole number of chunks
+        if chunk_length > 0 {
+            self.out_channel.send(Msg::WriteSram { data: chunk, length: chunk_length, offset: sram_offset as u16 }).await;
+            sram_offset += chunk_length;
+        }
+        if sram_offset > 0 {
+            self.last_error = None;
+            self.out_channel.send(Msg::WriteSramEnd).await;
+            // The dumper acknowledges with End, reporting a refused write on the way
+            let receiver = self.in_channel.receiver();
+            loop {
+                match receiver.receive().await {
+                    Msg::Error {code, context} => {
+                        self.last_error = Some((code, context));
+                    },
+                    Msg::End => {
+                        break;
+                    },
+                    _ => {}
+                }
+            }
+        }
+        self.busy.store(false, Ordering::Relaxed);
     }
 
     async fn generate_send_object_response(&mut self, buffer: &mut [u8]) -> usize {
-        let _ = self.read_packet(&mut buffer[0..64]).await;
-        match self.read_packet(&mut buffer[64..128]).await {
-            Ok(n) if n > 0 => {
-                match self.parse_mtp_command(&buffer, MtpContainerType::Data) {
+        if self.pending_upload == SRAM_HANDLE {
+            self.receive_sram(buffer).await;
+            self.pending_upload = 0x00000003;
+            return 0;
+        }
+        if self.pending_upload != 0x00000003 {
+            self.receive_uploaded_object(buffer).await;
+            self.sent_object = Some(self.pending_upload);
+            self.pending_upload = 0x00000003;
+            return 0;
+        }
+        let received = self.read_container(buffer).await;
+        match received {
+            n if n > 0 => {
+                match self.parse_mtp_command(&buffer[..received], MtpContainerType::Data) {
                     Ok(cmd) => {
-                        match cmd.op_code {
-                            0x100d => {
-                                self.configuration_file.fill(0);
-                                self.configuration_file_size = core::cmp::min(cmd.payload.len(), self.configuration_file.len());
-                                self.configuration_file[..self.configuration_file_size].copy_from_slice(&cmd.payload[..self.configuration_file_size]);
-                                match serde_json_core::from_slice::<DumperConfig>(&self.configuration_file[..self.configuration_file_size]) {
-                                    Ok((config, _)) => {
+                        match MtpOpCode::try_from(cmd.op_code) {
+                            Ok(MtpOpCode::SendObject) => {
+                                // Checked in a scratch copy first, a rejected file leaves config.json as it was
+                                let mut json = [0u8; CONFIG_JSON_MAX_SIZE];
+                                let limit = core::cmp::min(json.len(), self.configuration_file.len());
+                                let size = strip_json_comments(cmd.payload, &mut json[..limit]);
+                                let parsed = DumperConfig::from_json(&json[..size]);
+                                if parsed.is_some() {
+                                    self.configuration_file.fill(0);
+                                    self.configuration_file[..size].copy_from_slice(&json[..size]);
+                                    self.configuration_file_size = size;
+                                }
+                                match parsed {
+                                    Some(_) if self.edit_lock => {
+                                        self.configuration_file_deleted = false;
+                                    }
+                                    Some(config) => {
                                         self.send_updated_dumper_config(&config).await;
+                                        self.configuration_file_deleted = false;
+                                        self.sent_object = Some(0x00000003);
+                                    }
+                                    None => {
+                                        // Keep running with the previous configuration
+                                        self.last_warning = Some(Msg::WARNING_CONFIG_REJECTED);
                                     }
-                                    _ => {}
                                 };
                             }
                             _ => {}
@@ -813,50 +2720,66 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
             }
             offset = end;
         }
-        if offset > 0 && offset % 64 == 0 {
-            match self.write_packet(&[]).await {
-                _ => {
-                    // Allow the USB stack some breathing room; not strictly required
-                    // but avoids busy‑looping if the host stalls communication.
-                    Timer::after_millis(1).await;
-                }
+        if self.needs_zlp(len) {
+            self.write_zlp().await;
+        }
+    }
+
+    /// A transfer must be terminated by a zero-length packet if and only if its total length,
+    /// container header included, is an exact multiple of the packet size: otherwise the last
+    /// packet is already a short one.
+    fn needs_zlp(&self, len: usize) -> bool {
+        len > 0 && len % self.max_packet_size() == 0
+    }
+
+    async fn write_zlp(&mut self) {
+        match self.write_packet(&[]).await {
+            _ => {
+                // Allow the USB stack some breathing room; not strictly required
+                // but avoids busy‑looping if the host stalls communication.
+                Timer::after_millis(1).await;
             }
         }
     }
 
+    #[deny(unreachable_patterns)]
     pub async fn handle_response<'a>(&mut self, cmd: PtpCommand<'a>) {
         let mut buf = [0u8; 1024];
+        let op_code = MtpOpCode::try_from(cmd.op_code);
 
         // Data block
         let mut len;
-        match cmd.op_code {
-            0x1001 => {
+        match op_code {
+            Ok(MtpOpCode::GetDeviceInfo) => {
                 len = self.generate_device_info_response(cmd.transaction_id, &mut buf);
             }
-            0x1004 => {
+            Ok(MtpOpCode::GetStorageIDs) => {
                 len = self.generate_storage_id_response(cmd.transaction_id, &mut buf);
             }
-            0x1005 => {
+            Ok(MtpOpCode::GetStorageInfo) => {
                 len = self.generate_storage_info_response(cmd.transaction_id, &mut buf, &cmd);
             }
-            0x1007 => {
+            Ok(MtpOpCode::GetObjectHandles) => {
                 len = self.generate_object_handles_response(cmd.transaction_id, &mut buf, &cmd);
             }
-            0x1008 => {
+            Ok(MtpOpCode::GetObjectInfo) => {
                 len = self.generate_object_info_response(cmd.transaction_id, &mut buf, &cmd);
             }
-            0x1009 => {
+            Ok(MtpOpCode::GetObject) => {
                 len = self.generate_object_response(cmd.transaction_id, &mut buf, &cmd).await;
             }
-            0x100b => {
-                len = self.generate_delete_object_response(&cmd);
-            }
-            0x100c => {
+            Ok(MtpOpCode::SendObjectInfo) => {
                 len = self.generate_send_object_info_response(&mut buf, &cmd).await;
             }
-            0x100d => {
+            Ok(MtpOpCode::SendObject) => {
                 len = self.generate_send_object_response(&mut buf).await;
             }
+            Ok(MtpOpCode::GetDevicePropDesc) => {
+                len = self.generate_device_prop_desc_response(cmd.transaction_id, &mut buf, &cmd);
+            }
+            Ok(MtpOpCode::GetDevicePropValue) => {
+                len = self.generate_device_prop_value_response(cmd.transaction_id, &mut buf, &cmd);
+            }
             _ => {
                 len = 0;
             }
@@ -866,86 +2789,111 @@ impl<'d, D: Driver<'d>> MtpClass<'d, D> {
         }
 
         // Response block
-        match cmd.op_code {
-            0x1001 => {
+        let mut removed_object = None;
+        match op_code {
+            Ok(MtpOpCode::GetDeviceInfo) => {
                 len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
             }
-            0x1002 => {
+            Ok(MtpOpCode::OpenSession) => {
                 len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
             }
-            0x1003 => {
+            Ok(MtpOpCode::CloseSession) => {
                 len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
             }
-            0x1004 => {
+            Ok(MtpOpCode::GetStorageIDs) => {
                 len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
             }
-            0x1005 => {
+            Ok(MtpOpCode::GetStorageInfo) => {
                 if len == 0 {
                     len = self.generate_error_response_block(cmd.transaction_id, &mut buf, MtpCommandError::StoreNotAvailable);
                 } else {
                     len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
                 }
             }
-            0x1007 => {
+            Ok(MtpOpCode::GetObjectHandles) => {
                 len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
             }
-            0x1008 => {
+            Ok(MtpOpCode::GetObjectInfo) => {
                 len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
             }
-            0x1009 => {
+            Ok(MtpOpCode::GetObject) => {
                 len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
             }
-            0x100b => {
+            Ok(MtpOpCode::DeleteObject) => {
+                let result = match self.delete_object(&cmd) {
+                    Ok(handle) => {
+                        removed_object = Some(handle);
+                        MtpCommandError::Ok
+                    }
+                    Err(error) => error,
+                };
+                len = self.generate_error_response_block(cmd.transaction_id, &mut buf, result);
+            }
+            Ok(MtpOpCode::SendObject) => {
                 len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
             }
-            0x100d => {
+            Ok(MtpOpCode::ResetDevice) => {
                 len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
             }
-            _ => {
+            Ok(MtpOpCode::BeginEditObject) => {
+                let result = self.begin_edit_object(&cmd);
+                len = self.generate_error_response_block(cmd.transaction_id, &mut buf, result);
+            }
+            Ok(MtpOpCode::TruncateObject) => {
+                let result = self.truncate_object(&cmd);
+                len = self.generate_error_response_block(cmd.transaction_id, &mut buf, result);
+            }
+            Ok(MtpOpCode::EndEditObject) => {
+                let result = self.end_edit_object(&cmd).await;
+                len = self.generate_error_response_block(cmd.transaction_id, &mut buf, result);
+            }
+            Ok(MtpOpCode::GetDevicePropDesc) | Ok(MtpOpCode::GetDevicePropValue) => {
+                if len == 0 {
+                    len = self.generate_error_response_block(cmd.transaction_id, &mut buf, MtpCommandError::DevicePropNotSupported);
+                } else {
+                    len = self.generate_ok_response_block(cmd.transaction_id, &mut buf);
+                }
+            }
+            Ok(MtpOpCode::SendObjectInfo) => {
+                // Already answered along with the data block
                 len = 0;
             }
+            Ok(_) => {
+                len = self.generate_error_response_block(cmd.transaction_id, &mut buf, MtpCommandError::OperationNotSupported);
+            }
+            Err(error) => {
+                len = self.generate_error_response_block(cmd.transaction_id, &mut buf, error);
+            }
         }
-        let mut offset = 0;
-        while offset < len {
-            let end = core::cmp::min(offset + self.max_packet_size(), len);
-            let chunk = &buf[offset..end];
-            match self.write_packet(&chunk).await {
-                _ => {
-                    // Allow the USB stack some breathing room; not strictly required
-                    // but avoids busy‑looping if the host stalls communication.
-                    Timer::after_millis(1).await;
+        if len > 0 {
+            self.write_response_buffer(&buf, len).await;
+        }
+
+        // Events, so the host refreshes its view of the storage
+        match op_code {
+            Ok(MtpOpCode::DeleteObject) => {
+                if let Some(handle) = removed_object {
+                    self.send_event(EVENT_OBJECT_REMOVED, cmd.transaction_id, handle).await;
                 }
             }
-            offset = end;
+            Ok(MtpOpCode::SendObject) => {
+                if let Some(handle) = self.sent_object.take() {
+                    self.send_event(EVENT_OBJECT_ADDED, cmd.transaction_id, handle).await;
+                }
+            }
+            _ => {}
         }
     }
 
     async fn send_updated_dumper_config(&mut self, dumper_config: &DumperConfig) {
-        let mut field = [0u8;Msg::DUMP_SETUP_DATA_CHANGED_LENGTH];
-        let mut value = [0u8;Msg::DUMP_SETUP_DATA_CHANGED_LENGTH];
-
-        field[.."mapper".len()].copy_from_slice("mapper".as_bytes());
-        value[..1].copy_from_slice(&[dumper_config.mapper]);
-        self.out_channel.send(Msg::DumpSetupDataChanged { field, value }).await;
-        field.fill(0);
-        value.fill(0);
-        field[.."prgsize".len()].copy_from_slice("prgsize".as_bytes());
-        value[..1].copy_from_slice(&[dumper_config.prgsize]);
-        self.out_channel.send(Msg::DumpSetupDataChanged { field, value }).await;
-        field.fill(0);
-        value.fill(0);
-        field[.."chrsize".len()].copy_from_slice("chrsize".as_bytes());
-        value[..1].copy_from_slice(&[dumper_config.chrsize]);
-        self.out_channel.send(Msg::DumpSetupDataChanged { field, value }).await;
-        field.fill(0);
-        value.fill(0);
-        field[.."prg".len()].copy_from_slice("prg".as_bytes());
-        value[..2].copy_from_slice(&dumper_config.prg.to_ne_bytes());
-        self.out_channel.send(Msg::DumpSetupDataChanged { field, value }).await;
-        field.fill(0);
-        value.fill(0);
-        field[.."chr".len()].copy_from_slice("chr".as_bytes());
-        value[..2].copy_from_slice(&dumper_config.chr.to_ne_bytes());
-        self.out_channel.send(Msg::DumpSetupDataChanged { field, value }).await;
+        // Every write erases the flash page, skip it when the stored record already matches
+        if flash_config::read().as_ref() != Some(dumper_config) && !flash_config::write(dumper_config) {
+            self.last_warning = Some(Msg::WARNING_CONFIG_NOT_SAVED);
+        }
+        self.dumper_config = *dumper_config;
+        // The last dump no longer says anything about the next one
+        self.nes_rom_size = None;
+        self.snes_rom_size = None;
+        self.out_channel.send(Msg::Reconfigure { config: *dumper_config }).await;
     }
 }
\ No newline at end of file
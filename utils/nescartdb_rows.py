@@ -0,0 +1,51 @@
+#!/usr/bin/env python3
+"""Prints NES_ROM_DATABASE rows for dumper-lib/src/rom_database.rs from a NES Cart DB export.
+
+    python3 utils/nescartdb_rows.py NesCarts.xml [--limit 100]
+
+Only boards with a single PRG chip are kept, the CRC of that chip being what the dumper computes
+over the PRG-ROM. The CHR CRC is the one of the single CHR chip, 0 on CHR-RAM boards. Boards with
+several PRG or CHR chips are skipped, their per-chip CRCs do not add up to the dumper's one.
+"""
+
+import argparse
+import xml.etree.ElementTree as ElementTree
+
+
+def size_kb(size):
+    return int(size.lower().rstrip("k"))
+
+
+def rows(path):
+    seen = set()
+    for game in ElementTree.parse(path).getroot().iter("game"):
+        for cartridge in game.iter("cartridge"):
+            for board in cartridge.iter("board"):
+                prg = board.findall("prg")
+                chr_ = board.findall("chr")
+                if len(prg) != 1 or len(chr_) > 1 or board.get("mapper") is None:
+                    continue
+                prg_crc = int(prg[0].get("crc"), 16)
+                if prg_crc in seen:
+                    continue
+                seen.add(prg_crc)
+                chr_crc = int(chr_[0].get("crc"), 16) if chr_ else 0
+                chr_kb = size_kb(chr_[0].get("size")) if chr_ else 0
+                yield (prg_crc, chr_crc, int(board.get("mapper")), size_kb(prg[0].get("size")),
+                       chr_kb, game.get("name"))
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("xml")
+    parser.add_argument("--limit", type=int, default=100)
+    arguments = parser.parse_args()
+    for index, (prg_crc, chr_crc, mapper, prg_kb, chr_kb, name) in enumerate(rows(arguments.xml)):
+        if index == arguments.limit:
+            break
+        name = name.replace("\\", "\\\\").replace('"', '\\"')
+        print(f'    (0x{prg_crc:08X}, Some(0x{chr_crc:08X}), {mapper}, {prg_kb}, {chr_kb}, "{name}"),')
+
+
+if __name__ == "__main__":
+    main()